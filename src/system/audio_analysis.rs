@@ -0,0 +1,395 @@
+//! offline analysis of chart tracks, nothing here needs an audio output.
+
+use crate::system::core_structs::ChartInfo;
+use crate::system::core_structs::Bpm;
+use kira::sound::static_sound::StaticSoundData;
+use std::f32::consts::PI;
+use std::ops::Range;
+use time::Duration;
+
+/// how many samples between two analysis frames
+const HOP_SIZE: usize = 512;
+/// how many samples does a single analysis frame contain
+const WINDOW_SIZE: usize = 1024;
+/// how many analysis frames are used as local average when normalizing onset strength
+const AVERAGE_FRAMES: usize = 16;
+/// a peak should be the largest one among this many frames on each side
+const PEAK_RADIUS: usize = 2;
+/// tempos around this will be prefered, avoids picking half or double tempo.
+const PREFERED_BPM: f32 = 120.0;
+
+/// how many samples does a bucket of the finest peak level contain
+const BASE_BUCKET: usize = 64;
+/// cutoff between low and mid band, in Hz
+const LOW_BAND_CUTOFF: f32 = 200.0;
+/// cutoff between mid and high band, in Hz
+const HIGH_BAND_CUTOFF: f32 = 2000.0;
+/// how many bands do we split track into, they are low, mid and high.
+pub const BAND_COUNT: usize = 3;
+
+/// bpm range used by [`crate::system::core_structs::ShapoistCore::detect_tempo()`]
+pub const DEFAULT_BPM_RANGE: Range<f32> = 60.0..240.0;
+
+#[derive(Clone, Debug, PartialEq)]
+/// result of [`detect_tempo()`]
+pub struct TempoEstimate {
+	pub bpm: f32,
+	/// where the first beat of the beat grid lands in the track
+	pub offset: Duration,
+	/// normalized, higher means the track has a clearer beat.
+	pub confidence: f32,
+}
+
+impl TempoEstimate {
+	/// write estimated bpm and offset into chart info, existing bpm linkers will be dropped.
+	pub fn apply_to(&self, info: &mut ChartInfo) {
+		info.bpm = Bpm {
+			start_bpm: self.bpm,
+			linkers: vec!(),
+		};
+		info.offset = self.offset;
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Default)]
+/// how strong does the track change at each analysis frame
+pub struct OnsetEnvelope {
+	/// non-negative, one value each analysis frame
+	pub strength: Vec<f32>,
+	/// analysis frames per second
+	pub frame_rate: f32,
+}
+
+impl OnsetEnvelope {
+	/// analyze given sound
+	pub fn new(sound: &StaticSoundData) -> Self {
+		let frame_rate = sound.sample_rate as f32 / HOP_SIZE as f32;
+		let samples: Vec<f32> = sound.frames.iter().map(|frame| (frame.left + frame.right) / 2.0).collect();
+		if samples.len() <= WINDOW_SIZE {
+			return Self {
+				strength: vec!(),
+				frame_rate,
+			}
+		}
+		let frame_count = (samples.len() - WINDOW_SIZE) / HOP_SIZE + 1;
+		let energies: Vec<f32> = (0..frame_count).map(|i| {
+			let window = &samples[i * HOP_SIZE..i * HOP_SIZE + WINDOW_SIZE];
+			// first order difference works as a cheap high pass, so transients weights more than sustained bass.
+			let energy = window.windows(2).map(|pair| (pair[1] - pair[0]).powi(2)).sum::<f32>() / WINDOW_SIZE as f32;
+			(1.0 + 1e3 * energy).ln()
+		}).collect();
+		let flux: Vec<f32> = std::iter::once(0.0).chain(energies.windows(2).map(|pair| (pair[1] - pair[0]).max(0.0))).collect();
+		let strength = (0..frame_count).map(|i| {
+			let start = i.saturating_sub(AVERAGE_FRAMES / 2);
+			let end = (i + AVERAGE_FRAMES / 2).min(frame_count);
+			let average = flux[start..end].iter().sum::<f32>() / (end - start) as f32;
+			(flux[i] - average).max(0.0)
+		}).collect();
+		Self {
+			strength,
+			frame_rate,
+		}
+	}
+
+	/// when does given analysis frame happen in the track
+	pub fn time_of(&self, index: f32) -> Duration {
+		// a frame reacts most on the newest hop it contains
+		let delay = (WINDOW_SIZE - HOP_SIZE / 2) as f32 / HOP_SIZE as f32;
+		Duration::seconds_f32((index + delay) / self.frame_rate)
+	}
+
+	/// pick onsets from local peaks, sorted by time.
+	///
+	/// `density` is normalized, 1.0 keeps every peak while 0.0 only keeps the strongest one.
+	pub fn onsets(&self, density: f32) -> Vec<Onset> {
+		let strength = &self.strength;
+		let mut peaks: Vec<Onset> = (0..strength.len()).filter(|i| {
+			let start = i.saturating_sub(PEAK_RADIUS);
+			let end = (i + PEAK_RADIUS + 1).min(strength.len());
+			strength[*i] > 0.0 && strength[start..end].iter().all(|value| *value <= strength[*i]) && (start..*i).all(|j| strength[j] < strength[*i])
+		}).map(|i| Onset {
+			time: self.time_of(i as f32),
+			strength: strength[i],
+		}).collect();
+		if peaks.is_empty() {
+			return peaks;
+		}
+		let keep = ((peaks.len() as f32 * density.clamp(0.0, 1.0)).ceil() as usize).max(1);
+		peaks.sort_by(|a, b| b.strength.total_cmp(&a.strength));
+		peaks.truncate(keep);
+		peaks.sort_by(|a, b| a.time.cmp(&b.time));
+		peaks
+	}
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// where something starts to sound in the track
+pub struct Onset {
+	pub time: Duration,
+	pub strength: f32,
+}
+
+/// estimate bpm and first beat of given sound, None if the track is too short or has no clear beat.
+pub fn detect_tempo(sound: &StaticSoundData, bpm_range: Range<f32>) -> Option<TempoEstimate> {
+	let envelope = OnsetEnvelope::new(sound);
+	let strength = &envelope.strength;
+	let min_lag = ((envelope.frame_rate * 60.0 / bpm_range.end).floor() as usize).max(2);
+	let max_lag = (envelope.frame_rate * 60.0 / bpm_range.start).ceil() as usize;
+	if min_lag > max_lag || strength.len() < max_lag * 4 {
+		return None;
+	}
+
+	let autocorrelation = |lag: usize| -> f32 {
+		strength.iter().zip(&strength[lag..]).map(|(a, b)| a * b).sum::<f32>() / (strength.len() - lag) as f32
+	};
+	let energy = autocorrelation(0);
+	if energy <= 0.0 {
+		return None;
+	}
+	// one more lag on each side for interpolating
+	let first = min_lag - 1;
+	let correlations: Vec<f32> = (first..=max_lag + 1).map(autocorrelation).collect();
+	let mut best = min_lag;
+	let mut best_value = f32::MIN;
+	for lag in min_lag..=max_lag {
+		let bpm = envelope.frame_rate * 60.0 / lag as f32;
+		let weight = (-0.5 * (bpm / PREFERED_BPM).log2().powi(2)).exp();
+		let value = correlations[lag - first] * weight;
+		if value > best_value {
+			best_value = value;
+			best = lag;
+		}
+	}
+	let left = correlations[best - first - 1];
+	let center = correlations[best - first];
+	let right = correlations[best - first + 1];
+	let denominator = left - 2.0 * center + right;
+	let shift = if denominator.abs() > f32::EPSILON {
+		(0.5 * (left - right) / denominator).clamp(-0.5, 0.5)
+	}else {
+		0.0
+	};
+	let period = best as f32 + shift;
+	let mean = correlations.iter().sum::<f32>() / correlations.len() as f32;
+	let confidence = if energy > mean {
+		((center - mean) / (energy - mean)).clamp(0.0, 1.0)
+	}else {
+		0.0
+	};
+
+	let mut best_phase = 0;
+	let mut best_score = f32::MIN;
+	for phase in 0..period.ceil() as usize {
+		let score = (0..)
+			.map(|beat| (phase as f32 + beat as f32 * period).round() as usize)
+			.take_while(|index| *index < strength.len())
+			.map(|index| strength[index])
+			.sum::<f32>();
+		if score > best_score {
+			best_score = score;
+			best_phase = phase;
+		}
+	}
+
+	Some(TempoEstimate {
+		bpm: envelope.frame_rate * 60.0 / period,
+		offset: envelope.time_of(best_phase as f32),
+		confidence,
+	})
+}
+
+/// decoded chart track with multi-resolution peaks and band energies, used for drawing timelines.
+pub struct TrackAnalysis {
+	/// the decoded track
+	pub sound: StaticSoundData,
+	/// level n contains buckets of `64 * 2^n` samples, the last level contains only one bucket.
+	pub peaks: Vec<Vec<Peak>>,
+	/// mean square energy of low, mid and high band, layout is the same as `peaks`.
+	pub bands: Vec<Vec<[f32; BAND_COUNT]>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+/// min and max sample inside a bucket
+pub struct Peak {
+	pub min: f32,
+	pub max: f32,
+}
+
+impl Peak {
+	/// a peak covers both
+	pub fn merge(self, other: Peak) -> Peak {
+		Peak {
+			min: self.min.min(other.min),
+			max: self.max.max(other.max),
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Default)]
+/// waveform data of a time range, see [`TrackAnalysis::slice()`]
+pub struct WaveformSlice {
+	pub range: Range<Duration>,
+	/// one peak each bucket
+	pub peaks: Vec<Peak>,
+	/// rms of low, mid and high band each bucket, None if not requested.
+	pub bands: Option<Vec<[f32; BAND_COUNT]>>,
+}
+
+impl TrackAnalysis {
+	/// analyze given sound, takes a single pass over the whole track.
+	pub fn new(sound: StaticSoundData) -> Self {
+		let sample_rate = sound.sample_rate as f32;
+		let coefficient = |cutoff: f32| 1.0 - (-2.0 * PI * cutoff / sample_rate).exp();
+		let low_coefficient = coefficient(LOW_BAND_CUTOFF);
+		let high_coefficient = coefficient(HIGH_BAND_CUTOFF);
+		let mut low = 0.0;
+		let mut below_high = 0.0;
+		let mut peaks = vec!();
+		let mut bands = vec!();
+		for chunk in sound.frames.chunks(BASE_BUCKET) {
+			let mut peak = Peak {
+				min: f32::MAX,
+				max: f32::MIN,
+			};
+			let mut energy = [0.0; BAND_COUNT];
+			for frame in chunk {
+				let sample = (frame.left + frame.right) / 2.0;
+				peak.min = peak.min.min(sample);
+				peak.max = peak.max.max(sample);
+				// one pole low passes, cheap enough for a whole track
+				low += low_coefficient * (sample - low);
+				below_high += high_coefficient * (sample - below_high);
+				energy[0] += low * low;
+				energy[1] += (below_high - low).powi(2);
+				energy[2] += (sample - below_high).powi(2);
+			}
+			for value in &mut energy {
+				*value /= chunk.len() as f32;
+			}
+			peaks.push(peak);
+			bands.push(energy);
+		}
+
+		let mut peak_levels = vec!(peaks);
+		while let Some(last) = peak_levels.last().filter(|level| level.len() > 1) {
+			let next = last.chunks(2).map(|pair| pair.iter().copied().reduce(Peak::merge).unwrap_or_default()).collect();
+			peak_levels.push(next);
+		}
+		let mut band_levels = vec!(bands);
+		while let Some(last) = band_levels.last().filter(|level| level.len() > 1) {
+			let next = last.chunks(2).map(average_bands).collect();
+			band_levels.push(next);
+		}
+
+		Self {
+			sound,
+			peaks: peak_levels,
+			bands: band_levels,
+		}
+	}
+
+	/// get waveform between `range` with `buckets` points, picks the coarsest level that still has enough detail.
+	pub fn slice(&self, range: Range<Duration>, buckets: usize, with_bands: bool) -> WaveformSlice {
+		let sample_rate = self.sound.sample_rate as f64;
+		let total = self.sound.frames.len();
+		let to_sample = |time: Duration| ((time.as_seconds_f64() * sample_rate).max(0.0) as usize).min(total);
+		let start = to_sample(range.start);
+		let end = to_sample(range.end);
+		let mut output = WaveformSlice {
+			range,
+			peaks: vec!(),
+			bands: if with_bands { Some(vec!()) } else { None },
+		};
+		if buckets == 0 || end <= start || self.peaks[0].is_empty() {
+			return output;
+		}
+
+		let samples_per_bucket = (end - start) as f64 / buckets as f64;
+		let mut level = 0;
+		while level + 1 < self.peaks.len() && (BASE_BUCKET << (level + 1)) as f64 <= samples_per_bucket {
+			level += 1;
+		}
+		let bucket_size = BASE_BUCKET << level;
+		for i in 0..buckets {
+			let from = start + (i as f64 * samples_per_bucket) as usize;
+			let to = (start + ((i + 1) as f64 * samples_per_bucket) as usize).clamp(from + 1, total);
+			if samples_per_bucket < BASE_BUCKET as f64 {
+				// zoomed in deeper than the finest level, read samples directly
+				output.peaks.push(self.sound.frames[from.min(total - 1)..to].iter().map(|frame| {
+					let sample = (frame.left + frame.right) / 2.0;
+					Peak {
+						min: sample,
+						max: sample,
+					}
+				}).reduce(Peak::merge).unwrap_or_default());
+			}else {
+				let last = ((to - 1) / bucket_size).min(self.peaks[level].len() - 1);
+				let first = (from / bucket_size).min(last);
+				output.peaks.push(self.peaks[level][first..=last].iter().copied().reduce(Peak::merge).unwrap_or_default());
+			}
+			if let Some(bands) = &mut output.bands {
+				let level = if samples_per_bucket < BASE_BUCKET as f64 { 0 } else { level };
+				let bucket_size = BASE_BUCKET << level;
+				let last = ((to - 1) / bucket_size).min(self.bands[level].len() - 1);
+				let first = (from / bucket_size).min(last);
+				bands.push(average_bands(&self.bands[level][first..=last]).map(f32::sqrt));
+			}
+		}
+		output
+	}
+}
+
+fn average_bands(input: &[[f32; BAND_COUNT]]) -> [f32; BAND_COUNT] {
+	let mut output = [0.0; BAND_COUNT];
+	for bands in input {
+		for (sum, value) in output.iter_mut().zip(bands) {
+			*sum += value / input.len() as f32;
+		}
+	}
+	output
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use kira::dsp::Frame;
+	use kira::sound::static_sound::StaticSoundSettings;
+
+	/// short decaying clicks at given bpm, first one at `first_beat`
+	fn click_track(bpm: f32, first_beat: f32, length: f32) -> StaticSoundData {
+		let sample_rate = 44100;
+		let period = 60.0 / bpm;
+		let frames: Vec<Frame> = (0..(length * sample_rate as f32) as usize).map(|i| {
+			let time = i as f32 / sample_rate as f32;
+			let since_beat = if time < first_beat {
+				f32::MAX
+			}else {
+				(time - first_beat) % period
+			};
+			let value = if since_beat < 0.01 {
+				(since_beat * 2000.0 * std::f32::consts::TAU).sin() * (1.0 - since_beat / 0.01)
+			}else {
+				0.0
+			};
+			Frame::from_mono(value)
+		}).collect();
+		StaticSoundData {
+			sample_rate,
+			frames: frames.into(),
+			settings: StaticSoundSettings::default(),
+		}
+	}
+
+	#[test]
+	fn detects_click_track_tempo() {
+		let estimate = detect_tempo(&click_track(120.0, 0.25, 20.0), DEFAULT_BPM_RANGE).expect("click track has a clear beat");
+		assert!((estimate.bpm - 120.0).abs() < 1.0, "bpm: {}", estimate.bpm);
+		assert!((estimate.offset.as_seconds_f32() - 0.25).abs() < 0.03, "offset: {}", estimate.offset);
+		assert!(estimate.confidence > 0.5, "confidence: {}", estimate.confidence);
+	}
+
+	#[test]
+	fn silence_has_no_tempo() {
+		assert_eq!(detect_tempo(&click_track(120.0, 30.0, 20.0), DEFAULT_BPM_RANGE), None);
+	}
+}
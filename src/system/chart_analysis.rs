@@ -0,0 +1,283 @@
+//! offline analysis of charts, nothing here needs the track.
+
+use crate::system::core_structs::Bpm;
+use crate::system::core_structs::BpmLinkerType;
+use crate::system::core_structs::Chart;
+use crate::system::core_structs::ChartInfo;
+use crate::system::core_structs::Diffculty;
+use crate::system::core_structs::JudgeType;
+use crate::system::core_structs::Note;
+use crate::system::core_structs::serialize_duration;
+use crate::system::core_structs::deserialize_duration;
+use crate::system::core_structs::serialize_option_duration;
+use crate::system::core_structs::deserialize_option_duration;
+use nablo_shape::prelude::Vec2;
+use std::collections::HashMap;
+use std::ops::Range;
+use time::Duration;
+
+/// how many beats does a section contain
+const SECTION_BEATS: f32 = 8.0;
+/// strains of sections are sorted and weighted by this, so the hardest parts decide the rating.
+const SECTION_DECAY: f32 = 0.9;
+/// bpm which makes no change to rhythm strain
+const REFERENCE_BPM: f32 = 150.0;
+
+/// a suggested difficulty and where it comes from
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct DifficultyEstimate {
+	/// how hard is hitting notes in time, from note density, judge types and bpm.
+	pub rhythm: f32,
+	/// how hard is moving between notes, from jumps between judge fields and notes played while holding.
+	pub movement: f32,
+	/// every section of the chart, sorted by time.
+	pub sections: Vec<SectionDifficulty>,
+}
+
+/// difficulty of a part of a chart
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct SectionDifficulty {
+	pub range: Range<Duration>,
+	pub notes: usize,
+	/// notes per second
+	pub density: f32,
+	/// average weight of judge types, 1.0 for all taps.
+	pub mix: f32,
+	/// distance moved between judge fields per second, in chart diagonals.
+	pub jumps: f32,
+	/// notes played while holding other notes, per second.
+	pub hold_overlap: f32,
+	pub bpm: f32,
+	/// strain used by [`DifficultyEstimate::rhythm`]
+	pub rhythm: f32,
+	/// strain used by [`DifficultyEstimate::movement`]
+	pub movement: f32,
+}
+
+/// numbers of a chart for song select and the editor
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+pub struct ChartStats {
+	pub total_notes: usize,
+	/// keyed by [`JudgeType::kind()`]
+	pub notes_per_type: HashMap<String, usize>,
+	/// keyed by judge field id
+	pub notes_per_field: HashMap<String, usize>,
+	/// notes per second of each [`DENSITY_STEP`], starting from zero.
+	pub density: Vec<f32>,
+	/// highest notes per second in [`ChartStats::density`]
+	pub peak_density: f32,
+	/// sum of durations of every hold note
+	#[serde(serialize_with = "serialize_duration")]
+	#[serde(deserialize_with = "deserialize_duration")]
+	pub total_hold_time: Duration,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	#[serde(serialize_with = "serialize_option_duration")]
+	#[serde(deserialize_with = "deserialize_option_duration")]
+	pub first_note: Option<Duration>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	#[serde(serialize_with = "serialize_option_duration")]
+	#[serde(deserialize_with = "deserialize_option_duration")]
+	pub last_note: Option<Duration>,
+}
+
+/// length of each value in [`ChartStats::density`]
+pub const DENSITY_STEP: Duration = Duration::seconds(1);
+
+impl ChartStats {
+	/// count everything from notes of a chart
+	pub fn new(chart: &Chart, info: &ChartInfo) -> Self {
+		let mut stats = Self::default();
+		let end = chart.notes.values().map(|note| note.judge_time).max().unwrap_or_default().max(info.sustain_time);
+		let buckets = (end / DENSITY_STEP).max(0.0).floor() as usize + 1;
+		let mut counts = vec![0_usize; buckets];
+		for note in chart.notes.values() {
+			stats.total_notes += 1;
+			*stats.notes_per_type.entry(note.judge_type.kind()).or_default() += 1;
+			*stats.notes_per_field.entry(note.judge_field_id.clone()).or_default() += 1;
+			if let JudgeType::Hold(sustain) = note.judge_type {
+				stats.total_hold_time += sustain;
+			}
+			stats.first_note = Some(stats.first_note.map_or(note.judge_time, |time| time.min(note.judge_time)));
+			stats.last_note = Some(stats.last_note.map_or(note.judge_time, |time| time.max(note.judge_time)));
+			// notes before zero are counted into the first step
+			let index = (note.judge_time / DENSITY_STEP).max(0.0).floor() as usize;
+			counts[index.min(buckets - 1)] += 1;
+		}
+		let step = DENSITY_STEP.as_seconds_f32();
+		stats.density = counts.into_iter().map(|count| count as f32 / step).collect();
+		stats.peak_density = stats.density.iter().copied().fold(0.0, f32::max);
+		stats
+	}
+
+	/// density as points of a graph, x is time in seconds and y is notes per second.
+	pub fn density_points(&self) -> Vec<Vec2> {
+		let step = DENSITY_STEP.as_seconds_f32();
+		self.density.iter().enumerate().map(|(index, density)| Vec2::new(index as f32 * step, *density)).collect()
+	}
+}
+
+impl JudgeType {
+	/// name of this type without its data, custom types use their own name.
+	pub fn kind(&self) -> String {
+		match self {
+			Self::Tap => "Tap".into(),
+			Self::Slide => "Slide".into(),
+			Self::Flick => "Flick".into(),
+			Self::Hold(_) => "Hold".into(),
+			Self::TapAndFlick => "TapAndFlick".into(),
+			Self::Chain(_) => "Chain".into(),
+			Self::TapChain(_) => "TapChain".into(),
+			Self::AngledFilck(_) => "AngledFilck".into(),
+			Self::AngledTapFilck(_) => "AngledTapFilck".into(),
+			Self::Custom(name) => name.clone(),
+		}
+	}
+}
+
+impl DifficultyEstimate {
+	/// the difficulty we suggest, saves as `Shapoist(rhythm, movement)`.
+	pub fn suggested(&self) -> Diffculty {
+		Diffculty::Shapoist(round_level(self.rhythm), round_level(self.movement))
+	}
+
+	/// sections which drive the rating most, hardest first.
+	pub fn driving_sections(&self, count: usize) -> Vec<&SectionDifficulty> {
+		let mut sections: Vec<&SectionDifficulty> = self.sections.iter().collect();
+		sections.sort_by(|a, b| (b.rhythm + b.movement).total_cmp(&(a.rhythm + a.movement)));
+		sections.truncate(count);
+		sections
+	}
+}
+
+/// estimate difficulty of a chart from its notes
+pub fn estimate_difficulty(chart: &Chart, info: &ChartInfo) -> DifficultyEstimate {
+	let mut notes: Vec<&Note> = chart.notes.values().collect();
+	notes.sort_by_key(|note| note.judge_time);
+	let Some(last) = notes.last() else {
+		return DifficultyEstimate::default();
+	};
+	let end = last.judge_time.max(info.sustain_time);
+	let centers: HashMap<&String, Vec2> = chart.judge_fields.iter().map(|(id, field)| (id, field.area().center())).collect();
+	let diagonal = chart.size.len().max(1.0);
+	let holds: Vec<(&String, Range<Duration>)> = notes.iter().filter_map(|note| match note.judge_type {
+		JudgeType::Hold(sustain) => Some((&note.note_id, note.judge_time..note.judge_time + sustain)),
+		_ => None,
+	}).collect();
+
+	let mut sections = vec!();
+	let mut start = notes[0].judge_time.min(Duration::ZERO);
+	let mut index = 0;
+	let mut last_position: Option<Vec2> = None;
+	while start <= end {
+		let bpm = bpm_at(&info.bpm, start);
+		let length = if bpm > 0.0 {
+			Duration::seconds_f32(SECTION_BEATS * 60.0 / bpm)
+		}else {
+			Duration::seconds(4)
+		};
+		let range = start..start + length;
+		let mut section = SectionDifficulty {
+			range: range.clone(),
+			bpm,
+			..Default::default()
+		};
+		let mut weight = 0.0;
+		let mut distance = 0.0;
+		let mut overlap = 0;
+		while index < notes.len() && range.contains(&notes[index].judge_time) {
+			let note = notes[index];
+			section.notes += 1;
+			weight += type_weight(&note.judge_type);
+			if let Some(position) = centers.get(&note.judge_field_id) {
+				if let Some(last) = last_position {
+					distance += (*position - last).len() / diagonal;
+				}
+				last_position = Some(*position);
+			}
+			overlap += holds.iter().filter(|(id, span)| *id != &note.note_id && span.start < note.judge_time && span.end > note.judge_time).count();
+			index += 1;
+		}
+		let seconds = length.as_seconds_f32();
+		section.density = section.notes as f32 / seconds;
+		section.mix = if section.notes == 0 {
+			0.0
+		}else {
+			weight / section.notes as f32
+		};
+		section.jumps = distance / seconds;
+		section.hold_overlap = overlap as f32 / seconds;
+		section.rhythm = section.density * section.mix * (bpm / REFERENCE_BPM).max(0.0).sqrt();
+		section.movement = section.jumps * 3.0 + section.hold_overlap * 1.5;
+		sections.push(section);
+		start = range.end;
+	}
+
+	DifficultyEstimate {
+		rhythm: level_of(sections.iter().map(|section| section.rhythm).collect()),
+		movement: level_of(sections.iter().map(|section| section.movement).collect()),
+		sections,
+	}
+}
+
+/// how hard is a judge type compared to a tap
+fn type_weight(judge_type: &JudgeType) -> f32 {
+	match judge_type {
+		JudgeType::Tap => 1.0,
+		JudgeType::Slide => 0.5,
+		JudgeType::Flick => 1.2,
+		JudgeType::Hold(_) => 1.1,
+		JudgeType::TapAndFlick => 1.4,
+		JudgeType::Chain(steps) => 1.2 + 0.1 * steps.len() as f32,
+		JudgeType::TapChain(steps) => 1.4 + 0.1 * steps.len() as f32,
+		JudgeType::AngledFilck(_) => 1.4,
+		JudgeType::AngledTapFilck(_) => 1.6,
+		JudgeType::Custom(_) => 1.0,
+	}
+}
+
+/// turn strains of sections into a level, hardest sections weight most.
+fn level_of(mut strains: Vec<f32>) -> f32 {
+	strains.sort_by(|a, b| b.total_cmp(a));
+	let mut total = 0.0;
+	let mut weights = 0.0;
+	let mut weight = 1.0;
+	for strain in strains {
+		total += strain * weight;
+		weights += weight;
+		weight *= SECTION_DECAY;
+	}
+	if weights == 0.0 {
+		return 0.0;
+	}
+	2.0 * (total / weights).powf(0.75)
+}
+
+fn round_level(level: f32) -> f32 {
+	(level * 10.0).round() / 10.0
+}
+
+/// bpm at given time, each linker lasts [`crate::system::core_structs::BpmLinker::time`] and moves bpm towards its own.
+pub(crate) fn bpm_at(bpm: &Bpm, time: Duration) -> f32 {
+	let mut elapsed = Duration::ZERO;
+	let mut current = bpm.start_bpm;
+	for linker in &bpm.linkers {
+		if time < elapsed + linker.time {
+			let progress = ((time - elapsed) / linker.time) as f32;
+			return match linker.linker {
+				BpmLinkerType::Mutation => current,
+				BpmLinkerType::Power(n) => current + (linker.bpm - current) * progress.max(0.0).powf(n),
+				BpmLinkerType::Linear | BpmLinkerType::Bezier(_, _) => current + (linker.bpm - current) * progress,
+			};
+		}
+		elapsed += linker.time;
+		current = linker.bpm;
+	}
+	current
+}
+
+/// FNV-1a hash of given content as hex, stays the same between runs and platforms.
+pub(crate) fn content_hash(content: &[u8]) -> String {
+	let hash = content.iter().fold(0xcbf29ce484222325_u64, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3));
+	format!("{:016x}", hash)
+}
@@ -1,17 +1,26 @@
+use crate::system::audio_analysis::*;
+use crate::system::chart_analysis::*;
+use crate::system::judge_rule::*;
+use crate::system::modifier::*;
+use crate::system::scoring::*;
+use crate::system::rating::*;
 use crate::CLICK_SOUND;
 use crate::DELAY_ADJUSTMENT;
 use nablo_shape::prelude::Vec2;
 use std::ops::RangeFrom;
+use std::ops::Range;
 use nablo_shape::prelude::Area;
 use kira::tween::Tween;
 use nablo_shape::prelude::shape_elements::Style;
 use crate::system::ChartEditError;
 use nablo_data::CanBeAnimated;
 use time::Duration;
+use time::OffsetDateTime;
 use kira::manager::AudioManager;
 use kira::manager::backend::DefaultBackend;
 use kira::manager::AudioManagerSettings;
 use crate::system::PlayError;
+use crate::system::ReplayError;
 use crate::system::command::Command;
 use crate::system::command::CommandError;
 use crate::system::ChartError;
@@ -26,12 +35,17 @@ use std::ffi::OsStr;
 use crate::system::core_structs::ChartInfo;
 use crate::system::core_structs::NetWork;
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::Arc;
 use crate::system::timer::Timer;
 use crate::system::core_structs::Settings;
 use crate::system::Error::IoError;
 use crate::system::Error;
 use crate::system::io_functions::*;
+use std::path::Path;
 use std::path::PathBuf;
+use std::io::Read;
+use std::io::Write;
 use crate::system::core_structs::*;
 use log::*;
 
@@ -60,7 +74,9 @@ impl ShapoistCore {
 		let settings = Settings::process_setting_file(assets_path)?;
 		let log_path = PathBuf::from(process_log_path(assets_path, &settings)?);
 		let mut chart_list = process_chart_path(assets_path)?;
+		process_chart_history(assets_path, &mut chart_list)?;
 		let mut script_list = process_script_path(assets_path)?;
+		let play_history = process_play_history(assets_path)?;
 
 		if settings.need_check_chart {
 			for chart in &mut chart_list {
@@ -74,14 +90,17 @@ impl ShapoistCore {
 			}
 		}
 
-		Ok(Self {
+		let mut core = Self {
 			assets_path: assets_path.into(),
 			log_path,
 			settings,
 			chart_list,
 			script_list,
+			play_history,
 			..Default::default()
-		})
+		};
+		core.index_chart_stats();
+		Ok(core)
 	}
 
 	/// if settings have changed you should call this
@@ -96,7 +115,9 @@ impl ShapoistCore {
 		let assets_path = format!("{}/", self.assets_path.display());
 		let settings = Settings::process_setting_file(&assets_path)?;
 		let mut chart_list = process_chart_path(&assets_path)?;
+		process_chart_history(&assets_path, &mut chart_list)?;
 		let mut script_list = process_script_path(&assets_path)?;
+		let play_history = process_play_history(&assets_path)?;
 
 		if settings.need_check_chart {
 			for chart in &mut chart_list {
@@ -113,6 +134,8 @@ impl ShapoistCore {
 		self.settings = settings;
 		self.chart_list = chart_list;
 		self.script_list = script_list;
+		self.play_history = play_history;
+		self.index_chart_stats();
 		Ok(())
 	}
 
@@ -127,6 +150,8 @@ impl ShapoistCore {
 		};
 		match parse_toml::<Chart>(&chart) {
 			Ok(inner) => {
+				info.stats = Some(ChartStats::new(&inner, info));
+				self.cache_chart_stats(info);
 				self.current_chart = Some((inner, info.clone()));
 				Ok(())
 			},
@@ -136,6 +161,65 @@ impl ShapoistCore {
 		}
 	} 
 
+	/// compute [`ChartInfo::stats`] for every chart in [`ShapoistCore::chart_list`], broken charts are skipped.
+	///
+	/// stats are cached in `shapoist_assets/user/chart_stats.toml` keyed by content hash, only charts changed since last index are parsed.
+	pub fn index_chart_stats(&mut self) {
+		let cache_path = format!("{}/shapoist_assets/user/chart_stats.toml", self.assets_path.display());
+		let cache: HashMap<String, ChartStats> = match read_file_to_string(&cache_path).and_then(|cache| parse_toml(&cache)) {
+			Ok(t) => t,
+			Err(e) => {
+				info!("chart stats cache isn't used, indexing every chart. info: {}", e);
+				HashMap::new()
+			}
+		};
+		let mut new_cache = HashMap::new();
+		for info in &mut self.chart_list {
+			let content = match read_file_to_string(format!("{}/chart.sc", info.path.display())) {
+				Ok(t) => t,
+				Err(e) => {
+					warn!("can't index chart {}, info: {}", info.path.display(), e);
+					continue;
+				}
+			};
+			// stats also depend on length of the chart
+			let key = content_hash(format!("{}{}", content, info.sustain_time.whole_nanoseconds()).as_bytes());
+			let stats = match cache.get(&key) {
+				Some(stats) => stats.clone(),
+				None => match parse_toml::<Chart>(&content) {
+					Ok(chart) => ChartStats::new(&chart, info),
+					Err(e) => {
+						warn!("can't index chart {}, info: {}", info.path.display(), e);
+						continue;
+					}
+				},
+			};
+			info.stats = Some(stats.clone());
+			new_cache.insert(key, stats);
+		}
+		if new_cache == cache {
+			return;
+		}
+		let result = to_toml(&new_cache).and_then(|cache| {
+			if !PathBuf::from(&cache_path).exists() {
+				create_file(&cache_path)?;
+			}
+			write_file(&cache_path, cache.as_bytes())
+		});
+		if let Err(e) = result {
+			warn!("can't save chart stats cache, info: {}", e);
+		}
+	}
+
+	/// copy stats of given chart into [`ShapoistCore::chart_list`]
+	fn cache_chart_stats(&mut self, info: &ChartInfo) {
+		for chart_info in &mut self.chart_list {
+			if chart_info.path == info.path {
+				chart_info.stats.clone_from(&info.stats);
+			}
+		}
+	}
+
 	/// create minimal core, used in wasm.
 	pub fn minimal() -> Self {
 		debug!("creating new minimal ShapoistCore struct..");
@@ -194,13 +278,15 @@ impl ShapoistCore {
 		}
 
 		if self.play_info.is_some() && self.timer.is_started() {
+			self.check_ab_loop()?;
 			trace!("detected is play a chart, updating each shape...");
 			let play_info = self.play_info.as_mut().unwrap();
 			play_info.frame(&mut self.timer, self.settings.offset, true, if let Some(editor) = &self.chart_editor {
 				editor.show_click_effect
 			}else {
 				true
-			}, &mut self.current_sound, &mut self.is_in_delay_adjustment, &self.settings)?;
+			}, &mut self.current_sound, &mut self.is_in_delay_adjustment, &self.settings, &self.judge_rules)?;
+			self.record_result()?;
 		}
 
 		if let (Some(editor), Some((chart, _))) = (&mut self.chart_editor, &self.current_chart) {
@@ -225,15 +311,81 @@ impl ShapoistCore {
 	}
 
 	pub fn update_render_queue(&mut self, will_play_music: bool) -> Result<(), Error> {
+		self.check_ab_loop()?;
 		if let Some(play_info) = &mut self.play_info {
 			play_info.frame(&mut self.timer, self.settings.offset, will_play_music, if let Some(editor) = &self.chart_editor {
 				editor.show_click_effect
 			}else {
 				true
-			}, &mut self.current_sound, &mut self.is_in_delay_adjustment, &self.settings)
+			}, &mut self.current_sound, &mut self.is_in_delay_adjustment, &self.settings, &self.judge_rules)?;
 		}else {
-			Err(PlayError::HaventStart.into())
+			return Err(PlayError::HaventStart.into());
+		}
+		self.record_result()
+	}
+
+	/// save result of a finished play into [`ShapoistCore::play_history`] and [`ChartInfo::history`], auto plays, replays, practices and plays in editor are skipped.
+	fn record_result(&mut self) -> Result<(), Error> {
+		let Some(play_info) = &mut self.play_info else {
+			return Ok(());
+		};
+		let Some(result) = &play_info.result else {
+			return Ok(());
+		};
+		if play_info.is_result_recorded {
+			return Ok(());
+		}
+		play_info.is_result_recorded = true;
+		if !matches!(play_info.play_mode, PlayMode::Normal) || play_info.is_practice || self.chart_editor.is_some() {
+			return Ok(());
+		}
+		let Some((_, info)) = &mut self.current_chart else {
+			return Ok(());
+		};
+		let record = PlayRecord {
+			date: OffsetDateTime::now_utc(),
+			chart: info.identity(),
+			// replays are saved on their own
+			result: PlayResult {
+				replay: None,
+				..result.clone()
+			},
+			offset: self.settings.offset,
+			modifiers: result.modifiers.clone(),
+		};
+		// only the new record is appended, so older records are never rewritten
+		let history_path = format!("{}/shapoist_assets/user/history.toml", self.assets_path.display());
+		if !PathBuf::from(&history_path).exists() {
+			create_file(&history_path)?;
+		}
+		append_file(history_path, to_toml(&PlayHistory { records: vec!(record.clone()) })?.as_bytes())?;
+		self.play_history.records.push(record);
+		let history = info.history.get_or_insert_with(|| ChartHistory {
+			scoring: result.scoring.clone(),
+			..Default::default()
+		});
+		if !history.merge(result) {
+			// scoring system has changed, history follows the one used now
+			info!("scoring system of {} changed, rebuilding its history", info.song_name);
+			info.history = self.play_history.chart_history(&info.identity(), &result.scoring);
+		}
+		info!("saving play history of {}", info.song_name);
+		for chart_info in &mut self.chart_list {
+			if chart_info.path == info.path {
+				chart_info.history.clone_from(&info.history);
+			}
+		}
+		self.save_chart_history()
+	}
+
+	/// save [`ChartInfo::history`] of every chart into `shapoist_assets/user/chart_history.toml`, keyed by chart folder name.
+	fn save_chart_history(&self) -> Result<(), Error> {
+		let histories: HashMap<String, ChartHistory> = self.chart_list.iter().filter_map(|info| Some((info.folder_name()?, info.history.clone()?))).collect();
+		let history_path = format!("{}/shapoist_assets/user/chart_history.toml", self.assets_path.display());
+		if !PathBuf::from(&history_path).exists() {
+			create_file(&history_path)?;
 		}
+		write_file(history_path, to_toml(&histories)?.as_bytes())
 	}
 
 	/// clear playing
@@ -249,100 +401,115 @@ impl ShapoistCore {
 		self.chart_editor = None;
 	}
 
-	/// as name says
-	pub fn play_with_time(&mut self, play_mode: PlayMode, time: Duration) -> Result<(), Error> {
-		self.play(play_mode)?;
+	/// as name says, unlike [`ShapoistCore::seek()`] this won't make the play a practice.
+	pub fn play_with_time(&mut self, play_mode: PlayMode, modifiers: Vec<Modifier>, time: Duration) -> Result<(), Error> {
+		self.play(play_mode, modifiers)?;
 		debug!("setting timer...");
 		self.pause()?;
-		self.timer.set_to(time + Duration::seconds(3));
-		if let Some(t) = &mut self.play_info {
-			t.current_render = 0;
-		}
+		self.rebuild_at(time)?;
 		debug!("setted timer...");
 		self.resume()
 	}
 
-	/// start play current chart with given option, from start.
-	pub fn play(&mut self, play_mode: PlayMode) -> Result<(), Error> {
-		debug!("start playing..");
-		let (mut chart, info) = if let Some((chart, info)) = &mut self.current_chart {
-			let out = chart.clone();
-			(out, info)
+	/// jump to given chart time while playing, the play becomes a practice. see [`PlayInfo::is_practice`]
+	///
+	/// judge state, render queue, combo and events will be rebuilt to match the seek point, notes before it will be dropped.
+	pub fn seek(&mut self, time: Duration) -> Result<(), Error> {
+		self.rebuild_at(time)?;
+		if let Some(play_info) = &mut self.play_info {
+			play_info.is_practice = true;
+		}
+		Ok(())
+	}
+
+	fn rebuild_at(&mut self, time: Duration) -> Result<(), Error> {
+		debug!("seeking to {}", time);
+		let chart = if let Some((chart, _)) = &self.current_chart {
+			chart
 		}else {
 			return Err(PlayError::NoChartLoaded.into());
 		};
-		debug!("moving sources");
-		let mut shapes = vec!();
-		for (_, shape) in chart.shapes.into_iter() {
-			shapes.push(shape);
-		}
-		shapes.sort_by(|a, b| a.start_time.cmp(&b.start_time));
-		let mut judge_fields = HashMap::new();
-		for (id, judge_field) in chart.judge_fields.into_iter() {
-			judge_fields.insert(id.clone(), (judge_field, JudgeInfo {
-				current_judge: 0,
-				judge_tracks: vec!()
-			}));
-		}
-		let mut notes: HashMap<String, Vec<Note>> = HashMap::new();
-		for (_, note) in chart.notes.into_iter() {
-			if let Some(t)  = notes.get_mut(&note.judge_field_id) {
-				t.push(note);
-			}else {
-				notes.insert(note.judge_field_id.clone(), vec!(note));
+		if let Some(play_info) = &mut self.play_info {
+			let end = match &play_info.ab_loop {
+				Some(ab_loop) => ab_loop.end,
+				None => Duration::MAX,
 			};
+			let chart = apply_modifiers(chart, &play_info.modifiers);
+			play_info.seek(&chart, time..end)?;
+		}else {
+			return Err(PlayError::HaventStart.into());
+		}
+		self.timer.set_to(time + self.lead_in());
+		Ok(())
+	}
+
+	/// loop chart between `start` and `end` for practicing, will seek to `start` immediately.
+	///
+	/// statistics of each iteration are saved in [`AbLoop::statistics`]
+	pub fn set_ab_loop(&mut self, start: Duration, end: Duration) -> Result<(), Error> {
+		if let Some(play_info) = &mut self.play_info {
+			let end = end.min(play_info.sustain_time);
+			if start >= end {
+				return Err(PlayError::InvalidLoopRange.into());
+			}
+			info!("looping from {} to {}", start, end);
+			play_info.ab_loop = Some(AbLoop {
+				start,
+				end,
+				..Default::default()
+			});
+		}else {
+			return Err(PlayError::HaventStart.into());
+		}
+		self.seek(start)
+	}
+
+	/// stop looping, returns the loop we were in (if any).
+	pub fn clear_ab_loop(&mut self) -> Result<Option<AbLoop>, Error> {
+		let ab_loop = if let Some(play_info) = &mut self.play_info {
+			play_info.ab_loop.take()
+		}else {
+			return Err(PlayError::HaventStart.into());
+		};
+		// notes after loop end were dropped while looping, so bring them back
+		if ab_loop.is_some() {
+			let current = self.current()?;
+			self.seek(current)?;
 		}
-		let mut total_notes = 0;
-		for vec in notes.values_mut() {
-			vec.sort_by(|a, b| a.judge_time.cmp(&b.judge_time));
-			total_notes += vec.len();
+		Ok(ab_loop)
+	}
+
+	/// seek back to loop start if we reached loop end, notes near loop end are given their whole judge window.
+	fn check_ab_loop(&mut self) -> Result<(), Error> {
+		let current = self.current()?;
+		let start = if let Some(play_info) = &self.play_info {
+			match &play_info.ab_loop {
+				Some(ab_loop) if current >= ab_loop.end + play_info.judge_profile.windows().fade => ab_loop.start,
+				_ => return Ok(()),
+			}
+		}else {
+			return Ok(())
+		};
+		if let Some(play_info) = &mut self.play_info {
+			play_info.finish_loop_iteration();
 		}
+		debug!("reached loop end, seeking back");
+		self.seek(start)
+	}
+
+	/// start play current chart with given option and modifiers, from start.
+	pub fn play(&mut self, play_mode: PlayMode, modifiers: Vec<Modifier>) -> Result<(), Error> {
+		debug!("start playing..");
+		let Some((chart, info)) = &self.current_chart else {
+			return Err(PlayError::NoChartLoaded.into());
+		};
+		let lead_in = self.resolve_lead_in(info);
 		debug!("setting audio...");
 		let audio_manager = match AudioManager::<DefaultBackend>::new(AudioManagerSettings::default()) {
 			Ok(t) => t,
 			Err(e) => return Err(PlayError::ManagerCreateFail(e).into()),
 		};
-		let click_sound = match StaticSoundData::from_cursor(std::io::Cursor::new(CLICK_SOUND), StaticSoundSettings::default().volume(self.settings.click_sound_volume as f64)) {
-			Ok(t) => t,
-			Err(e) => {
-				return Err(ChartError::MusicSourceCantReadString(e.to_string()).into());
-			}
-		};
-		chart.events.sort_by(|a, b| a.time.cmp(&b.time));
-		let events = chart.events;
-		let play_info = Some(PlayInfo {
-			shapes,
-			judge_fields,
-			notes,
-			judge_vec: vec!(),
-			render_queue: vec!(),
-			score: 0.,
-			accuracy: 0.,
-			combo: 0,
-			max_combo: 0,
-			play_mode,
-			replay: Replay::default(),
-			audio_manager,
-			total_notes,
-			judged_notes: 0,
-			click_effects: chart.click_effects.clone(),
-			is_finished: false,
-			sustain_time: info.sustain_time,
-			current_render: 0,
-			offset: info.offset,
-			is_track_played: false,
-			track_path: format!("{}/song.mp3",info.path.display()).into(),
-			#[cfg(target_arch = "wasm32")]
-			sound_data: vec!(),
-			judged_note_id: vec!(),
-			click_sound,
-			click_sound_handle: None,
-			events,
-			current_event: 0,
-			current_shader: None,
-			shaders: chart.shaders.clone(),
-		});
-		self.play_info = play_info;
+		self.play_info = Some(PlayInfo::new(chart, info, play_mode, modifiers, &self.settings, lead_in, Some(audio_manager))?);
 		debug!("setting timer...");
 		self.timer = Timer::default();
 		self.timer.start();
@@ -353,37 +520,14 @@ impl ShapoistCore {
 	/// if chart have changed, you should call this
 	pub fn refresh_play_info(&mut self) -> Result<(), Error> {
 		let chart = if let Some((chart, _)) = &mut self.current_chart {
-			chart.clone()
+			chart
 		}else {
 			return Err(PlayError::NoChartLoaded.into());
 		};
 		if let Some(play_info) = &mut self.play_info {
 			debug!("moving sources");
-			let mut shapes = vec!();
-			for (_, shape) in chart.shapes.into_iter() {
-				shapes.push(shape);
-			}
-			shapes.sort_by(|a, b| a.start_time.cmp(&b.start_time));
-			let mut judge_fields = HashMap::new();
-			for (id, judge_field) in chart.judge_fields.into_iter() {
-				judge_fields.insert(id.clone(), (judge_field, JudgeInfo {
-					current_judge: 0,
-					judge_tracks: vec!()
-				}));
-			}
-			let mut notes: HashMap<String, Vec<Note>> = HashMap::new();
-			for (_, note) in chart.notes.into_iter() {
-				if let Some(t)  = notes.get_mut(&note.judge_field_id) {
-					t.push(note);
-				}else {
-					notes.insert(note.judge_field_id.clone(), vec!(note));
-				};
-			}
-			let mut total_notes = 0;
-			for vec in notes.values_mut() {
-				vec.sort_by(|a, b| a.judge_time.cmp(&b.judge_time));
-				total_notes += vec.len();
-			}
+			let chart = apply_modifiers(chart, &play_info.modifiers);
+			let (shapes, judge_fields, notes, total_notes) = split_chart(&chart, Duration::MIN..Duration::MAX);
 			play_info.shapes = shapes;
 			play_info.judge_fields = judge_fields;
 			play_info.notes = notes;
@@ -400,11 +544,13 @@ impl ShapoistCore {
 	pub fn pause(&mut self) -> Result<(), Error> {
 		if let Some(play_info) = &mut self.play_info {
 			play_info.is_track_played = false;
-			if let Err(e) = play_info.audio_manager.pause(Tween {
-				duration: std::time::Duration::from_secs(1),
-				..Default::default()
-			}) {
-				return Err(PlayError::from(e).into());
+			if let Some(audio_manager) = &mut play_info.audio_manager {
+				if let Err(e) = audio_manager.pause(Tween {
+					duration: std::time::Duration::from_secs(1),
+					..Default::default()
+				}) {
+					return Err(PlayError::from(e).into());
+				}
 			}
 		}else {
 			return Err(PlayError::HaventStart.into())
@@ -416,11 +562,13 @@ impl ShapoistCore {
 	/// resume playing
 	pub fn resume(&mut self) -> Result<(), Error> {
 		if let Some(play_info) = &mut self.play_info {
-			if let Err(e) = play_info.audio_manager.resume(Tween {
-				duration: std::time::Duration::from_secs(1),
-				..Default::default()
-			}) {
-				return Err(PlayError::from(e).into());
+			if let Some(audio_manager) = &mut play_info.audio_manager {
+				if let Err(e) = audio_manager.resume(Tween {
+					duration: std::time::Duration::from_secs(1),
+					..Default::default()
+				}) {
+					return Err(PlayError::from(e).into());
+				}
 			}
 		}else {
 			return Err(PlayError::HaventStart.into())
@@ -431,7 +579,24 @@ impl ShapoistCore {
 
 	/// get current time
 	pub fn current(&mut self) -> Result<Duration, Error> {
-		Ok(self.timer.read() - Duration::seconds(3))
+		Ok(self.timer.read() - self.lead_in())
+	}
+
+	/// get how long do we wait before chart starts in current play, [`DEFAULT_LEAD_IN`] if not playing.
+	pub fn lead_in(&self) -> Duration {
+		match &self.play_info {
+			Some(play_info) => play_info.lead_in,
+			None => DEFAULT_LEAD_IN,
+		}
+	}
+
+	/// editor asks for no lead in, otherwise settings overrides chart.
+	fn resolve_lead_in(&self, info: &ChartInfo) -> Duration {
+		if self.chart_editor.is_some() {
+			Duration::ZERO
+		}else {
+			self.settings.lead_in.or(info.lead_in).unwrap_or(DEFAULT_LEAD_IN)
+		}
 	}
 
 	/// single select an element
@@ -498,12 +663,12 @@ impl ShapoistCore {
 		Ok(())
 	}
 
-	/// set chart play time
+	/// set chart play time, same as [`ShapoistCore::seek()`] while playing.
 	pub fn set(&mut self, input: Duration) -> Result<(), Error> {
-		self.timer.set(input + Duration::seconds(3));
-		if let Some(t) = &mut self.play_info {
-			t.current_render = 0;
+		if self.play_info.is_some() {
+			return self.seek(input);
 		}
+		self.timer.set_to(input + self.lead_in());
 		Ok(())
 	}
 
@@ -513,7 +678,7 @@ impl ShapoistCore {
 			return Err(PlayError::NoChartLoaded.into())
 		}
 		self.chart_editor = Some(Default::default());
-		self.play(PlayMode::Auto)?;
+		self.play(PlayMode::Auto, vec!())?;
 		self.timer = Timer::default();
 		self.timer.start();
 		self.pause()?;
@@ -524,15 +689,130 @@ impl ShapoistCore {
 	pub fn judge(&mut self, event: JudgeEvent) -> Result<(), Error> {
 		debug!("judging notes...");
 		if let Some(play_info) = &mut self.play_info {
-			play_info.judge(event, &self.timer, if let Some(editor) = &self.chart_editor {
+			if !matches!(play_info.play_mode, PlayMode::Normal) {
+				return Ok(());
+			}
+			let time = self.timer.read() - play_info.lead_in;
+			let event = play_info.record_event(event, time, &self.settings.key_bindings);
+			play_info.judge(event, time, if let Some(editor) = &self.chart_editor {
 				editor.show_click_effect
 			}else {
 				true
-			})?;
+			}, &self.settings, &self.judge_rules)?;
 		}
 		Ok(())
 	}
 
+	/// best performance of each played chart, highest first.
+	///
+	/// computed from current difficulties in [`ShapoistCore::chart_list`], so changing difficulty of a chart changes its performance too.
+	/// plays of charts not in chart list are skipped.
+	pub fn performances(&self) -> Vec<Performance> {
+		let mut best: HashMap<usize, Performance> = HashMap::new();
+		let charts: Vec<(ChartIdentity, &Diffculty)> = self.chart_list.iter().map(|info| {
+			// unsaved changes of current chart count as well
+			match &self.current_chart {
+				Some((_, current)) if current.path == info.path => (current.identity(), &current.diffculty),
+				_ => (info.identity(), &info.diffculty),
+			}
+		}).collect();
+		for record in &self.play_history.records {
+			let Some(index) = charts.iter().position(|(identity, _)| identity == &record.chart) else {
+				continue;
+			};
+			let Some(value) = performance_of(&record.result, charts[index].1) else {
+				continue;
+			};
+			if !best.get(&index).is_some_and(|performance| performance.value >= value) {
+				best.insert(index, Performance {
+					chart: record.chart.clone(),
+					date: record.date,
+					value,
+				});
+			}
+		}
+		let mut performances: Vec<Performance> = best.into_values().collect();
+		performances.sort_by(|a, b| b.value.total_cmp(&a.value));
+		performances
+	}
+
+	/// player rating, average of best [`Settings::rating_count`] performances.
+	pub fn rating(&self) -> f32 {
+		rating_of(&self.performances(), self.settings.rating_count)
+	}
+
+	/// save replay of the finished play into `shapoist_assets/user/replays`, returns where it is saved.
+	pub fn save_replay(&self) -> Result<PathBuf, Error> {
+		let Some(replay) = self.play_info.as_ref().and_then(|play_info| play_info.result.as_ref()).and_then(|result| result.replay.as_ref()) else {
+			return Err(ReplayError::NothingToSave.into());
+		};
+		let Some((chart, info)) = &self.current_chart else {
+			return Err(PlayError::NoChartLoaded.into());
+		};
+		let date = replay.date.unwrap_or_else(OffsetDateTime::now_utc);
+		let header = ReplayHeader {
+			chart: info.identity(),
+			chart_hash: chart.content_hash()?,
+			core_version: env!("CARGO_PKG_VERSION").into(),
+			judge_profile: replay.judge_profile.clone(),
+			offset: replay.offset,
+			player_name: self.settings.player_name.clone(),
+			date,
+		};
+		let replay_path = format!("{}/shapoist_assets/user/replays", self.assets_path.display());
+		if !PathBuf::from(&replay_path).exists() {
+			create_dir(&replay_path)?;
+		}
+		let path = PathBuf::from(format!("{}/{}.{}", replay_path, date.unix_timestamp_nanos() / 1_000_000, REPLAY_EXTENSION));
+		info!("saving replay of {} into {}", info.song_name, path.display());
+		write_replay(&path, &header, replay)?;
+		Ok(path)
+	}
+
+	/// every saved replay with its header, latest first. files can't be read are skipped.
+	pub fn replay_list(&self) -> Result<Vec<(PathBuf, ReplayHeader)>, Error> {
+		let replay_path = format!("{}/shapoist_assets/user/replays", self.assets_path.display());
+		if !PathBuf::from(&replay_path).exists() {
+			return Ok(vec!());
+		}
+		let mut replays = vec!();
+		for path in read_every_file(&replay_path)? {
+			match read_replay_entry::<ReplayHeader>(&path, REPLAY_HEADER) {
+				Ok(header) => replays.push((PathBuf::from(path), header)),
+				Err(e) => error!("reading replay {} failed, info: {}", path, e),
+			}
+		}
+		replays.sort_by(|a, b| b.1.date.cmp(&a.1.date));
+		Ok(replays)
+	}
+
+	/// load a saved replay of current chart, watch it by passing it to [`ShapoistCore::play()`] with [`PlayMode::Replay`].
+	///
+	/// fails if the replay is recorded on another chart or by another version of shapoist core, or current chart has been modified since it was recorded.
+	pub fn load_replay<P: AsRef<Path>>(&self, path: P) -> Result<(ReplayHeader, Replay), Error> {
+		let Some((chart, info)) = &self.current_chart else {
+			return Err(PlayError::NoChartLoaded.into());
+		};
+		let header: ReplayHeader = read_replay_entry(&path, REPLAY_HEADER)?;
+		if header.chart != info.identity() {
+			return Err(ReplayError::ChartMismatch(header.chart.song_name).into());
+		}
+		if header.chart_hash != chart.content_hash()? {
+			return Err(ReplayError::ChartModified(info.song_name.clone()).into());
+		}
+		// judging may change between versions, so a replay only plays back the same on the core recorded it
+		if header.core_version != env!("CARGO_PKG_VERSION") {
+			return Err(ReplayError::VersionMismatch(header.core_version).into());
+		}
+		let replay = read_replay_entry(&path, REPLAY_DATA)?;
+		Ok((header, replay))
+	}
+
+	/// register how notes of [`JudgeType::Custom`] named `name` are judged, returns the rule it replaces.
+	pub fn register_judge_rule(&mut self, name: impl Into<String>, rule: impl JudgeRule + 'static) -> Option<Arc<dyn JudgeRule>> {
+		self.judge_rules.register(name, rule)
+	}
+
 	/// as name says
 	pub fn start_delay_adjustment(&mut self) -> Result<(), Error> {
 		info!("starting delay adjustment");
@@ -542,7 +822,7 @@ impl ShapoistCore {
 			sustain_time: Duration::seconds(16),
 			..Default::default()
 		}));
-		self.play(PlayMode::Auto)?;
+		self.play(PlayMode::Auto, vec!())?;
 		self.current_sound = match StaticSoundData::from_media_source(std::io::Cursor::new(DELAY_ADJUSTMENT), Default::default()) {
 			Ok(t) => Some(t),
 			Err(e) => {
@@ -555,14 +835,15 @@ impl ShapoistCore {
 	/// judge something, but in delay adjustment, returns true if adjustment finished
 	pub fn delay_adjustment_judge(&mut self, event: JudgeEvent) -> Result<bool, Error> {
 		debug!("judging notes in delay adjustment...");
-		let current = self.timer.read() - Duration::seconds(3);
+		let current = self.timer.read() - self.lead_in();
 		if current > Duration::seconds(16) {
 			self.is_in_delay_adjustment = false;
 			self.clear_play();
 			return Ok(true);
 		}
-		for click in event.clicks {
-			if let ClickState::Pressed = click.state {
+		let states = event.clicks.iter().map(|click| &click.state).chain(event.keypresses.iter().map(|keypress| &keypress.state));
+		for state in states {
+			if let ClickState::Pressed = state {
 				for i in 0..16 {
 					if (2 * i - 1) * Duration::milliseconds(500) <= current && current < (2 * i + 1) * Duration::milliseconds(500) {
 						self.adjustment.push(current - i * Duration::milliseconds(1000));
@@ -624,12 +905,138 @@ impl ShapoistCore {
 		Err(Error::PlatformUnsupport(String::from("wasm32")))
 	}
 
+	/// decode track of current chart, every chart will only be decoded once.
+	#[cfg(not(target_arch = "wasm32"))]
+	fn track_analysis(&mut self) -> Result<&TrackAnalysis, Error> {
+		let path = if let Some((_, info)) = &self.current_chart {
+			info.path.clone()
+		}else {
+			return Err(PlayError::NoChartLoaded.into())
+		};
+		if let std::collections::hash_map::Entry::Vacant(e) = self.track_cache.entry(path.clone()) {
+			info!("decoding track of {}", path.display());
+			let sound = match StaticSoundData::from_file(format!("{}/song.mp3", path.display()), StaticSoundSettings::default()) {
+				Ok(t) => t,
+				Err(e) => return Err(ChartError::MusicSourceCantRead(e).into()),
+			};
+			e.insert(TrackAnalysis::new(sound));
+		}
+		Ok(&self.track_cache[&path])
+	}
+
+	/// get waveform of current chart's track between `range` with `buckets` points, band energies are contained if `with_bands`.
+	#[cfg(not(target_arch = "wasm32"))]
+	pub fn waveform(&mut self, range: Range<Duration>, buckets: usize, with_bands: bool) -> Result<WaveformSlice, Error> {
+		Ok(self.track_analysis()?.slice(range, buckets, with_bands))
+	}
+
+	#[cfg(target_arch = "wasm32")]
+	/// get waveform of current chart's track between `range` with `buckets` points, band energies are contained if `with_bands`.
+	pub fn waveform(&mut self, _: Range<Duration>, _: usize, _: bool) -> Result<WaveformSlice, Error> {
+		Err(Error::PlatformUnsupport(String::from("wasm32")))
+	}
+
+	/// suggest a difficulty for current chart, see [`DifficultyEstimate::suggested()`]
+	pub fn estimate_difficulty(&self) -> Result<DifficultyEstimate, Error> {
+		if let Some((chart, info)) = &self.current_chart {
+			Ok(estimate_difficulty(chart, info))
+		}else {
+			Err(PlayError::NoChartLoaded.into())
+		}
+	}
+
+	/// estimate bpm and offset from track of current chart, use [`TempoEstimate::apply_to()`] to write it into chart info.
+	#[cfg(not(target_arch = "wasm32"))]
+	pub fn detect_tempo(&mut self) -> Result<TempoEstimate, Error> {
+		let sound = &self.track_analysis()?.sound;
+		match detect_tempo(sound, DEFAULT_BPM_RANGE) {
+			Some(t) => Ok(t),
+			None => Err(ChartError::NoClearBeat.into()),
+		}
+	}
+
+	#[cfg(target_arch = "wasm32")]
+	/// estimate bpm and offset from track of current chart, use [`TempoEstimate::apply_to()`] to write it into chart info.
+	pub fn detect_tempo(&mut self) -> Result<TempoEstimate, Error> {
+		Err(Error::PlatformUnsupport(String::from("wasm32")))
+	}
+
+	/// detect onsets from track of current chart and insert notes into given judge field as a rhythm draft, returns ids of generated notes.
+	///
+	/// when snapping, only [`Bpm::start_bpm`] is used.
+	#[cfg(not(target_arch = "wasm32"))]
+	pub fn generate_draft(&mut self, options: &DraftOptions) -> Result<Vec<String>, Error> {
+		let sound = self.track_analysis()?.sound.clone();
+		let (chart, info) = if let Some((chart, info)) = &mut self.current_chart {
+			(chart, info)
+		}else {
+			return Err(PlayError::NoChartLoaded.into())
+		};
+		let target = match chart.judge_fields.get(&options.judge_field_id) {
+			Some(field) => field.area().center(),
+			None => return Err(ChartEditError::JudgeFieldNotFound(options.judge_field_id.clone()).into()),
+		};
+		info!("generating draft for {}", info.song_name);
+		let step = options.snap.map(|division| Duration::seconds_f32(60.0 / info.bpm.start_bpm / division.max(1) as f32));
+		let mut times: Vec<Duration> = vec!();
+		for onset in OnsetEnvelope::new(&sound).onsets(options.density) {
+			// track plays `offset` ahead of chart
+			let mut time = onset.time - info.offset;
+			if let Some(step) = step {
+				time = step * (time / step).round() as f32;
+			}
+			if time < Duration::ZERO {
+				continue;
+			}
+			if let Some(last) = times.last() {
+				if time - *last < options.min_interval {
+					continue;
+				}
+			}
+			times.push(time);
+		}
+
+		let mut ids = vec!();
+		let mut index = 1;
+		for judge_time in times {
+			let note_id = loop {
+				let id = format!("draft #{}", index);
+				index += 1;
+				if !chart.notes.contains_key(&id) && !chart.shapes.contains_key(&id) {
+					break id;
+				}
+			};
+			chart.shapes.insert(note_id.clone(), options.note_shape(&note_id, judge_time, target));
+			chart.notes.insert(note_id.clone(), Note {
+				note_id: note_id.clone(),
+				judge_type: options.judge_type.clone(),
+				judge_time,
+				judge_field_id: options.judge_field_id.clone(),
+				click_effect_id: String::from("default"),
+				click_effect_position: target,
+				linked_shape: Some(vec!(note_id.clone())),
+			});
+			ids.push(note_id);
+		}
+		info!("{} notes generated", ids.len());
+		Ok(ids)
+	}
+
+	#[cfg(target_arch = "wasm32")]
+	/// detect onsets from track of current chart and insert notes into given judge field as a rhythm draft, returns ids of generated notes.
+	pub fn generate_draft(&mut self, _: &DraftOptions) -> Result<Vec<String>, Error> {
+		Err(Error::PlatformUnsupport(String::from("wasm32")))
+	}
+
 	/// as name says
 	pub fn save_current_chart(&mut self) -> Result<(), Error> {
-		if let Some((chart, info)) = &self.current_chart {
+		if let Some((chart, info)) = &mut self.current_chart {
 			let path = format!("{}",info.path.display());
 			write_file(format!("{}/chart.sc", path), to_toml(&chart)?.as_bytes())?;
 			write_file(format!("{}/config.toml", path), to_toml(&info)?.as_bytes())?;
+			info.stats = Some(ChartStats::new(chart, info));
+			let info = info.clone();
+			self.cache_chart_stats(&info);
 			Ok(())
 		}else {
 			Err(PlayError::NoChartLoaded.into())
@@ -650,6 +1057,7 @@ impl ShapoistCore {
 	/// as name says
 	pub fn delete_chart(&mut self, chart_info: &ChartInfo) -> Result<(), Error> {
 		self.chart_list.retain(|inner| inner != chart_info);
+		self.track_cache.remove(&chart_info.path);
 		remove_path(&chart_info.path)
 	}
 
@@ -794,6 +1202,53 @@ impl ShapoistCore {
 	}
 }
 
+/// split chart into what [`PlayInfo`] needs, notes whose judge time is out of `range` will be dropped.
+#[allow(clippy::type_complexity)]
+fn split_chart(chart: &Chart, range: Range<Duration>) -> (Vec<Shape>, HashMap<String, (JudgeField, JudgeInfo)>, HashMap<String, Vec<Note>>, usize) {
+	let mut shapes: Vec<Shape> = chart.shapes.values().cloned().collect();
+	shapes.sort_by(|a, b| a.start_time.cmp(&b.start_time));
+	let mut judge_fields = HashMap::new();
+	for (id, judge_field) in &chart.judge_fields {
+		judge_fields.insert(id.clone(), (judge_field.clone(), JudgeInfo {
+			current_judge: 0,
+			judge_tracks: vec!()
+		}));
+	}
+	let mut notes: HashMap<String, Vec<Note>> = HashMap::new();
+	for note in chart.notes.values() {
+		if !range.contains(&note.judge_time) {
+			continue;
+		}
+		if let Some(t)  = notes.get_mut(&note.judge_field_id) {
+			t.push(note.clone());
+		}else {
+			notes.insert(note.judge_field_id.clone(), vec!(note.clone()));
+		};
+	}
+	let mut total_notes = 0;
+	for vec in notes.values_mut() {
+		vec.sort_by(|a, b| a.judge_time.cmp(&b.judge_time));
+		total_notes += vec.len();
+	}
+	(shapes, judge_fields, notes, total_notes)
+}
+
+/// ids of clicks made by auto play, keeps away from touches and keys
+const AUTO_CLICK_IDS: Range<usize> = (usize::MAX / 4)..(usize::MAX / 2);
+
+/// gap between two clicks made by auto play for the same note
+const AUTO_STEP: Duration = Duration::milliseconds(10);
+
+/// width of each bucket in [`TimingHistogram`]
+const TIMING_HISTOGRAM_BUCKET: Duration = Duration::milliseconds(5);
+
+/// extension of saved replay files
+const REPLAY_EXTENSION: &str = "ssr";
+
+/// entries in a replay file
+const REPLAY_HEADER: &str = "header.toml";
+const REPLAY_DATA: &str = "replay.toml";
+
 #[allow(dead_code)]
 fn log_name_generate(assets_path: &str) -> String {
 	let now = time::OffsetDateTime::now_utc();
@@ -825,6 +1280,11 @@ impl Settings {
 }
 
 impl ChartInfo {
+	/// name of the folder this chart lives in, used to identify chart in user data
+	pub fn folder_name(&self) -> Option<String> {
+		Some(self.path.file_name()?.to_string_lossy().to_string())
+	}
+
 	/// read and process chart from path
 	pub fn process(path: &str) -> Result<ChartInfo, Error> {
 		info!("processing single chart");
@@ -936,36 +1396,135 @@ impl ScriptInfo {
 }
 
 impl PlayInfo {
-	/// call this function to render a single frame
+	/// prepare a play of given chart from start, `audio_manager` is None for judging without sound.
 	#[allow(clippy::too_many_arguments)]
-	pub fn frame(&mut self, timer: &mut Timer, offset: Duration, will_play_music: bool, show_click_effect: bool, sound: &mut Option<StaticSoundData>, is_in_delay_adjustment: &mut bool, settings: &Settings) -> Result<(), Error> {
-		let time = timer.read();
-		if self.is_finished {
-			debug!("play finished");
-			*is_in_delay_adjustment = false;
-			timer.pause();
-			if let Err(e) = self.audio_manager.pause(Tween {
-				duration: std::time::Duration::from_secs(1),
-				..Default::default()
-			}) {
-				return Err(PlayError::from(e).into());
-			}
+	pub(crate) fn new(chart: &Chart, info: &ChartInfo, play_mode: PlayMode, modifiers: Vec<Modifier>, settings: &Settings, lead_in: Duration, audio_manager: Option<AudioManager>) -> Result<Self, Error> {
+		// replays are watched with what they are recorded with
+		let modifiers = match &play_mode {
+			PlayMode::Replay(replay) => replay.modifiers.clone(),
+			_ => modifiers,
+		};
+		let mut chart = apply_modifiers(chart, &modifiers);
+		let mut judge_profile = settings.judge_windows.clone().or_else(|| info.judge_windows.clone()).unwrap_or_default();
+		let mut scoring = settings.scoring.clone().or_else(|| info.scoring.clone()).unwrap_or_default();
+		let mut life_gauge = settings.life_gauge.gauge();
+		let mut note_lock = settings.note_lock.clone();
+		if let PlayMode::Replay(replay) = &play_mode {
+			judge_profile = replay.judge_profile.clone();
+			scoring = replay.scoring.clone();
+			life_gauge = replay.life_gauge.clone();
+			note_lock = replay.note_lock.clone();
 		}
-
-		if !self.is_finished {
-			let play_time = if *is_in_delay_adjustment {
-				time + self.offset - Duration::seconds(3) 
-			}else { 
-				time + self.offset + offset - Duration::seconds(3) 
-			};
-			if !self.is_track_played && play_time > Duration::ZERO && will_play_music {
-				info!("music playing...");
-				let sound_setting = StaticSoundSettings::new().playback_region(RangeFrom { start: play_time.as_seconds_f64() }).volume(settings.music_volume as f64);
-				let static_sound = if let Some(sound) = sound {
-					sound.clone().with_settings(sound_setting)
-				}else {
-					cfg_if::cfg_if! {
-						if #[cfg(target_arch = "wasm32")] {
+		if modifiers.contains(&Modifier::NoFail) {
+			life_gauge.can_fail = false;
+		}
+		debug!("moving sources");
+		let (shapes, judge_fields, notes, total_notes) = split_chart(&chart, Duration::MIN..Duration::MAX);
+		let click_sound = match StaticSoundData::from_cursor(std::io::Cursor::new(CLICK_SOUND), StaticSoundSettings::default().volume(settings.click_sound_volume as f64)) {
+			Ok(t) => t,
+			Err(e) => {
+				return Err(ChartError::MusicSourceCantReadString(e.to_string()).into());
+			}
+		};
+		chart.events.sort_by(|a, b| a.time.cmp(&b.time));
+		let events = chart.events;
+		// filled while playing in normal mode
+		let replay = Replay {
+			modifiers: modifiers.clone(),
+			judge_profile: judge_profile.clone(),
+			note_lock: note_lock.clone(),
+			scoring: scoring.clone(),
+			life_gauge: life_gauge.clone(),
+			offset: settings.offset,
+			..Default::default()
+		};
+		Ok(Self {
+			shapes,
+			judge_fields,
+			notes,
+			judge_vec: vec!(),
+			score_history: vec!(),
+			note_lock,
+			replay_cursor: 0,
+			auto_clicks: vec!(),
+			auto_started: HashSet::new(),
+			auto_click_id: 0,
+			fast_count: 0,
+			late_count: 0,
+			timing_histogram: TimingHistogram::new(judge_profile.windows().fade, TIMING_HISTOGRAM_BUCKET),
+			render_queue: vec!(),
+			score: 0.,
+			accuracy: 0.,
+			combo: 0,
+			max_combo: 0,
+			play_mode,
+			replay,
+			modifiers,
+			audio_manager,
+			total_notes,
+			judged_notes: 0,
+			click_effects: chart.click_effects.clone(),
+			is_finished: false,
+			sustain_time: info.sustain_time,
+			current_render: 0,
+			offset: info.offset,
+			is_track_played: false,
+			track_path: format!("{}/song.mp3",info.path.display()).into(),
+			#[cfg(target_arch = "wasm32")]
+			sound_data: vec!(),
+			judged_note_id: HashSet::new(),
+			click_sound,
+			click_sound_handle: None,
+			events,
+			current_event: 0,
+			current_shader: None,
+			shaders: chart.shaders.clone(),
+			ab_loop: None,
+			lead_in,
+			judge_profile,
+			scoring,
+			life: life_gauge.initial,
+			life_gauge,
+			is_failed: false,
+			result: None,
+			is_practice: false,
+			is_result_recorded: false,
+			track_handle: None,
+		})
+	}
+
+	/// call this function to render a single frame
+	#[allow(clippy::too_many_arguments)]
+	pub fn frame(&mut self, timer: &mut Timer, offset: Duration, will_play_music: bool, show_click_effect: bool, sound: &mut Option<StaticSoundData>, is_in_delay_adjustment: &mut bool, settings: &Settings, rules: &JudgeRules) -> Result<(), Error> {
+		let time = timer.read();
+		if self.is_finished {
+			debug!("play finished");
+			*is_in_delay_adjustment = false;
+			timer.pause();
+			if let Some(audio_manager) = &mut self.audio_manager {
+				if let Err(e) = audio_manager.pause(Tween {
+					duration: std::time::Duration::from_secs(1),
+					..Default::default()
+				}) {
+					return Err(PlayError::from(e).into());
+				}
+			}
+		}
+
+		if !self.is_finished {
+			let play_time = if *is_in_delay_adjustment {
+				time + self.offset - self.lead_in
+			}else { 
+				time + self.offset + offset - self.lead_in
+			};
+			if !self.is_track_played && play_time > Duration::ZERO && will_play_music && self.audio_manager.is_some() {
+				info!("music playing...");
+				let sound_setting = StaticSoundSettings::new().playback_region(RangeFrom { start: play_time.as_seconds_f64() }).volume(settings.music_volume as f64);
+				let static_sound = if let Some(sound) = sound {
+					sound.clone().with_settings(sound_setting)
+				}else {
+					cfg_if::cfg_if! {
+						if #[cfg(target_arch = "wasm32")] {
 							let out = match StaticSoundData::from_media_source(std::io::Cursor::new(self.sound_data.clone()), sound_setting) {
 								Ok(t) => t,
 								Err(e) => return Err(ChartError::from(e).into()),
@@ -983,20 +1542,38 @@ impl PlayInfo {
 					}
 				};
 				
-				if let Err(e) = self.audio_manager.play(static_sound) {
-					return Err(PlayError::from(e).into());
-				};
+				if let Some(mut handle) = self.track_handle.take() {
+					if let Err(e) = handle.stop(Tween::default()) {
+						return Err(PlayError::from(e).into());
+					}
+				}
+				if let Some(audio_manager) = &mut self.audio_manager {
+					match audio_manager.play(static_sound) {
+						Ok(handle) => self.track_handle = Some(handle),
+						Err(e) => return Err(PlayError::from(e).into()),
+					};
+				}
 
 				self.is_track_played = true;
 				info!("music played");
 			}
 		}
 
-		if !self.is_finished && time > Duration::seconds(3) {
-			let time = time - Duration::seconds(3);
+		if !self.is_finished && time > self.lead_in {
+			let time = time - self.lead_in;
+			self.play_replay(time, show_click_effect, settings, rules)?;
 
-			if time > self.sustain_time {
+			if self.is_failed {
+				info!("life ran out, play failed");
+				if let Some(mut handle) = self.track_handle.take() {
+					if let Err(e) = handle.stop(Tween::default()) {
+						return Err(PlayError::from(e).into());
+					}
+				}
+			}
+			if time > self.sustain_time || self.is_failed {
 				self.is_finished = true;
+				self.result = Some(self.summarize(time, &settings.grades));
 				self.shapes.clear();
 				self.notes.clear();
 				self.judge_fields.clear();
@@ -1041,7 +1618,7 @@ impl PlayInfo {
 				self.judge_fields.remove(&id);
 			}
 
-			loop {
+			while self.current_event < self.events.len() {
 				if self.events[self.current_event].time > time {
 					break;
 				}
@@ -1055,18 +1632,255 @@ impl PlayInfo {
 				self.current_event += 1;
 			}
 		}
-		if let PlayMode::Auto = self.play_mode {
-			self.judge(JudgeEvent::default(), timer, show_click_effect)?;
+		// auto clicks of a replay are recorded in it
+		if matches!(self.play_mode, PlayMode::Auto) || (self.modifiers.contains(&Modifier::AutoFlick) && !matches!(self.play_mode, PlayMode::Replay(_))) {
+			self.auto_play(timer.read() - self.lead_in, show_click_effect, settings, rules)?;
 		}
 		Ok(())
 	}
 
-	fn judge(&mut self, event: JudgeEvent, timer: &Timer, show_click_effect: bool) -> Result<(), Error> {
-		let time = timer.read() - Duration::seconds(3);
-		if time < Duration::ZERO {
+	/// feed recorded events reached by `time` into judging, does nothing if we are not watching a replay.
+	fn play_replay(&mut self, time: Duration, show_click_effect: bool, settings: &Settings, rules: &JudgeRules) -> Result<(), Error> {
+		let PlayMode::Replay(replay) = &self.play_mode else {
+			return Ok(());
+		};
+		let end = self.replay_cursor + replay.judge_history_events[self.replay_cursor..].partition_point(|event| event.time.unwrap_or_default() <= time);
+		let events = replay.judge_history_events[self.replay_cursor..end].to_vec();
+		self.replay_cursor = end;
+		// only recorded events are judged, checking misses at frame time may judge a note before its click is fed
+		for event in events {
+			let event_time = event.time.unwrap_or_default();
+			self.judge(event, event_time, show_click_effect, settings, rules)?;
+		}
+		Ok(())
+	}
+
+	/// index of the first judgement whose score differs from the watching replay, None if nothing differs yet or we are not watching a replay.
+	///
+	/// scores are counted from the last seek, so this only makes sense for replays watched from start.
+	pub fn replay_divergence(&self) -> Option<usize> {
+		let PlayMode::Replay(replay) = &self.play_mode else {
+			return None;
+		};
+		self.score_history.iter().zip(&replay.score_history).position(|(score, recorded)| (score - recorded).abs() > 1e-3 * recorded.abs().max(1.0))
+	}
+
+	/// is this note played by auto play?
+	fn is_auto_note(play_mode: &PlayMode, auto_flick: bool, note: &Note) -> bool {
+		matches!(play_mode, PlayMode::Auto) || (auto_flick && Modifier::is_flick(note))
+	}
+
+	/// let a perfect player play every reached note, clicks go through the same judge path as normal play.
+	fn auto_play(&mut self, time: Duration, show_click_effect: bool, settings: &Settings, rules: &JudgeRules) -> Result<(), Error> {
+		let windows = self.judge_profile.windows();
+		let auto_flick = self.modifiers.contains(&Modifier::AutoFlick);
+		for (id, notes) in &self.notes {
+			// notes of removed fields can't be played
+			let Some((_, judge_track)) = self.judge_fields.get(id) else {
+				continue;
+			};
+			for note in notes.iter().skip(judge_track.current_judge) {
+				if note.judge_time > time {
+					break;
+				}
+				if !Self::is_auto_note(&self.play_mode, auto_flick, note) || self.auto_started.contains(&note.note_id) || self.judged_note_id.contains(&note.note_id) {
+					continue;
+				}
+				self.auto_started.insert(note.note_id.clone());
+				self.auto_click_id += 1;
+				let mut clicks = auto_clicks_of(note, rules.get(&note.judge_type).needs_press(note), &windows, AUTO_CLICK_IDS.start + self.auto_click_id);
+				self.auto_clicks.append(&mut clicks);
+			}
+		}
+		self.auto_clicks.sort_by_key(|click| click.time);
+		let due = self.auto_clicks.partition_point(|click| click.time <= time);
+		let mut due: Vec<AutoClick> = self.auto_clicks.drain(..due).collect();
+		// clicks at the same time are sent together, so a note is started before its following clicks arrive.
+		while !due.is_empty() {
+			let count = due.partition_point(|click| click.time == due[0].time);
+			let clicks = due.drain(..count).filter_map(|click| {
+				let (field, _) = self.judge_fields.get(&click.field_id)?;
+				Some(Click {
+					id: click.id,
+					position: field.area().center() + click.offset,
+					state: click.state,
+					key: None,
+					time: Some(click.time),
+				})
+			}).collect();
+			let event = self.record_event(JudgeEvent {
+				clicks,
+				..Default::default()
+			}, time, &settings.key_bindings);
+			self.judge(event, time, show_click_effect, settings, rules)?;
+		}
+		// misses are checked even if there is no click
+		let event = self.record_event(JudgeEvent::default(), time, &settings.key_bindings);
+		self.judge(event, time, show_click_effect, settings, rules)
+	}
+
+	/// summary of what has been played until `time`, notes haven't been judged count as missed.
+	fn summarize(&self, time: Duration, grades: &[GradeThreshold]) -> PlayResult {
+		let mut judges = JudgeCounts::default();
+		for record in &self.judge_vec {
+			judges.push(&record.judge);
+		}
+		judges.miss += self.total_notes.saturating_sub(self.judged_notes);
+		let clear_type = if self.is_failed {
+			ClearType::Failed
+		}else if judges.immaculate == self.total_notes {
+			ClearType::AllImmaculate
+		}else if judges.fade == 0 && judges.miss == 0 {
+			ClearType::FullCombo
+		}else {
+			ClearType::Clear
+		};
+		PlayResult {
+			judges,
+			total_notes: self.total_notes,
+			max_combo: self.max_combo,
+			accuracy: self.accuracy,
+			score: self.score,
+			scoring: self.scoring.clone(),
+			clear_type,
+			judge_profile: self.judge_profile.clone(),
+			// multipliers may push score over max score
+			grade: GradeThreshold::grade_of(grades, (self.score / self.scoring.max_score(self.total_notes)).min(1.0)),
+			modifiers: self.modifiers.clone(),
+			duration: time,
+			// practices are not whole plays, so they can't be replayed
+			replay: (matches!(self.play_mode, PlayMode::Normal) && !self.is_practice).then(|| Replay {
+				score_history: self.score_history.clone(),
+				date: Some(OffsetDateTime::now_utc()),
+				..self.replay.clone()
+			}),
+		}
+	}
+
+	/// rebuild everything to match the given chart time, notes outside `range` will be dropped.
+	pub(crate) fn seek(&mut self, chart: &Chart, range: Range<Duration>) -> Result<(), Error> {
+		let time = range.start;
+		let (shapes, judge_fields, notes, total_notes) = split_chart(chart, range);
+		self.shapes = shapes;
+		self.judge_fields = judge_fields;
+		self.notes = notes;
+		self.total_notes = total_notes;
+		self.click_effects.clone_from(&chart.click_effects);
+		self.judged_notes = 0;
+		self.judged_note_id.clear();
+		self.judge_vec.clear();
+		self.score_history.clear();
+		if let PlayMode::Replay(replay) = &self.play_mode {
+			self.replay_cursor = replay.judge_history_events.partition_point(|event| event.time.unwrap_or_default() < time);
+		}
+		self.life = self.life_gauge.initial;
+		self.is_failed = false;
+		self.result = None;
+		self.is_result_recorded = false;
+		self.auto_clicks.clear();
+		self.auto_started.clear();
+		self.fast_count = 0;
+		self.late_count = 0;
+		self.timing_histogram.clear();
+		self.score = 0.0;
+		self.accuracy = 0.0;
+		self.combo = 0;
+		self.max_combo = 0;
+		self.is_finished = false;
+
+		self.current_render = self.shapes.partition_point(|shape| shape.start_time <= time);
+		self.render_queue = self.shapes[..self.current_render].iter().filter(|shape| shape.start_time + shape.sustain_time >= time).cloned().collect();
+
+		self.events.clone_from(&chart.events);
+		self.events.sort_by(|a, b| a.time.cmp(&b.time));
+		self.current_event = self.events.partition_point(|event| event.time <= time);
+		self.current_shader = None;
+		for event in &self.events[..self.current_event] {
+			if let ChartEventInner::ChangeShader(id) = &event.inner {
+				self.current_shader = id.as_ref().and_then(|id| self.shaders.get(id).cloned());
+			}
+		}
+
+		if let Some(mut handle) = self.track_handle.take() {
+			if let Err(e) = handle.stop(Tween::default()) {
+				return Err(PlayError::from(e).into());
+			}
+		}
+		self.is_track_played = false;
+		Ok(())
+	}
+
+	/// save statistics of current loop iteration, see [`AbLoop`]. notes still unjudged count as missed.
+	pub(crate) fn finish_loop_iteration(&mut self) {
+		if self.ab_loop.is_none() {
+			return;
+		}
+		let missed: Vec<String> = self.notes.values().flatten().filter(|note| !self.judged_note_id.contains(&note.note_id)).map(|note| note.note_id.clone()).collect();
+		for note_id in missed {
+			self.caculate(JudgeRecord {
+				note_id: note_id.clone(),
+				judge: Judge::Miss,
+				offset: None,
+			});
+			self.judged_note_id.insert(note_id);
+		}
+		if let Some(ab_loop) = &mut self.ab_loop {
+			ab_loop.statistics.push(LoopStatistics {
+				iteration: ab_loop.iteration,
+				score: self.score,
+				accuracy: self.accuracy,
+				max_combo: self.max_combo,
+				total_notes: self.total_notes,
+				judges: self.judge_vec.clone(),
+				scoring: self.scoring.clone(),
+			});
+			ab_loop.iteration += 1;
+		}
+	}
+
+	/// turn key presses into clicks at center of bound judge fields
+	fn key_clicks(&self, keypresses: &[KeyPress], bindings: &HashMap<Key, String>) -> Vec<Click> {
+		keypresses.iter().filter_map(|keypress| {
+			let (field, _) = self.judge_fields.get(bindings.get(&keypress.key)?)?;
+			Some(Click {
+				// keeps away from ids of touches
+				id: usize::MAX - keypress.key as usize,
+				position: field.area().center(),
+				state: keypress.state.clone(),
+				key: Some(keypress.key),
+				time: keypress.time,
+			})
+		}).collect()
+	}
+
+	/// turn key presses into clicks and keep the event in [`PlayInfo::replay`], so replays don't depend on key bindings.
+	///
+	/// only normal plays are recorded, including clicks made by [`Modifier::AutoFlick`].
+	fn record_event(&mut self, mut event: JudgeEvent, time: Duration, bindings: &HashMap<Key, String>) -> JudgeEvent {
+		if !matches!(self.play_mode, PlayMode::Normal) || time < Duration::ZERO || self.is_finished || self.is_failed || self.is_practice {
+			return event;
+		}
+		let mut key_clicks = self.key_clicks(&event.keypresses, bindings);
+		event.clicks.append(&mut key_clicks);
+		event.keypresses.clear();
+		event.time = Some(time);
+		self.replay.judge_history_events.push(event.clone());
+		event
+	}
+
+	/// judge an event happened at `time` on play timeline
+	fn judge(&mut self, mut event: JudgeEvent, time: Duration, show_click_effect: bool, settings: &Settings, rules: &JudgeRules) -> Result<(), Error> {
+		if time < Duration::ZERO || self.is_finished || self.is_failed {
 			return Ok(())
 		}
-		let mut judges = vec!();
+		let mut key_clicks = self.key_clicks(&event.keypresses, &settings.key_bindings);
+		event.clicks.append(&mut key_clicks);
+		let windows = self.judge_profile.windows();
+		let auto_flick = self.modifiers.contains(&Modifier::AutoFlick);
+		// notes played by auto play only take clicks made by auto play, and so do notes played by player.
+		let is_owner = |note: &Note, click: &Click| Self::is_auto_note(&self.play_mode, auto_flick, note) == AUTO_CLICK_IDS.contains(&click.id);
+		// every judged note in this call, effects and scores will be applied at the end.
+		let mut judged: Vec<(Note, Judge, Option<Duration>)> = vec!();
 
 		let click_effect_position = |note: &Note, render_queue: &Vec<Shape>| -> Vec2 {
 			if let Some(id) = &note.linked_shape {
@@ -1094,233 +1908,170 @@ impl PlayInfo {
 			}
 		};
 
-		match self.play_mode {
-			PlayMode::Normal => {
-				for (id, (field, judge_track)) in &mut self.judge_fields {
-					if let Some(notes) = self.notes.get_mut(id) {
-						for click in &event.clicks {
-							judge_track.judge_tracks.retain_mut(|inner| {
-								let note = &notes[inner.note_id];
-								let mut judge: Option<Judge> = None;
-								match note.judge_type {
-									JudgeType::Flick => {
-										if inner.linked_click == click.id {
-											if (click.position - inner.last_position).len() > 5.0 {
-												judge = Some(Judge::Immaculate(1.0));
-											}
-											if (time - inner.start_time).abs() > Duration::milliseconds(120) {
-												judge = Some(Judge::Miss);
-											}
-										}
-										
-									},
-									JudgeType::TapAndFlick => {
-										if inner.linked_click == click.id {
-											if (click.position - inner.last_position).len() > 5.0 {
-												judge = Some(Judge::Immaculate(1.0));
-											}
-											if (time - inner.start_time).abs() > Duration::milliseconds(120) {
-												judge = Some(Judge::Miss);
-											}
-										}
-									},
-									JudgeType::Hold(sustain) => {
-										let delta = (inner.start_time - note.judge_time).abs();
-										let percent = ((time - note.judge_time) / sustain) as f32;
-										let judge_check = || -> Judge {
-											if delta < Duration::milliseconds(50) {
-												Judge::Immaculate((delta / Duration::milliseconds(50)) as f32)
-											}else if delta < Duration::milliseconds(70) {
-												Judge::Extra
-											}else if delta < Duration::milliseconds(120) {
-												Judge::Normal
-											}else if delta < Duration::milliseconds(150) {
-												Judge::Fade
-											}else {
-												Judge::Miss
-											}
-										};
-										if percent > 1.0 {
-											let mut judge_inner = judge_check();
-											if let Judge::Immaculate(inner) = &mut judge_inner {
-												*inner *= 0.995
-											}
-											judge = Some(judge_inner);
-										}else if percent > 0.0 {
-											if let ClickState::Released = click.state {
-												if percent < 0.8 {
-													judge = Some(Judge::Miss)
-												}else {
-													let mut judge_inner = judge_check();
-													if let Judge::Immaculate(inner) = &mut judge_inner {
-														*inner *= percent
-													}
-													judge = Some(judge_inner);
-												}
-											}
-										}
-									},
-									JudgeType::AngledFilck(angle) => {
-										let delta = click.position - inner.last_position;
-										let radio = (delta.angle() - angle).abs() / (std::f32::consts::PI / 12.0);
-										if inner.linked_click == click.id {
-											if delta.len() > 5.0 && radio < 1.0 {
-												judge = Some(Judge::Immaculate(1.0 * radio));
-											}
-											if (time - inner.start_time).abs() > Duration::milliseconds(120) {
-												judge = Some(Judge::Miss);
-											}
-										}
-									}
-									JudgeType::AngledTapFilck(angle) => {
-										let delta = click.position - inner.last_position;
-										let radio = (delta.angle() - angle).abs() / (std::f32::consts::PI / 12.0);
-										if inner.linked_click == click.id {
-											if delta.len() > 5.0 && radio < 1.0 {
-												judge = Some(Judge::Immaculate(1.0 * radio));
-											}
-											if (time - inner.start_time).abs() > Duration::milliseconds(120) {
-												judge = Some(Judge::Miss);
-											}
-										}
-									}
-									JudgeType::Chain(_) => todo!(),
-									JudgeType::TapChain(_) => todo!(),
-									_ => {},
-								}
-								if let Some(judge_inner) = &judge {
-									if show_click_effect {
-										let mut shapes = match self.click_effects.get(&note.click_effect_id) {
-											Some(t) => t.clone(),
-											None => Default::default()
-										}.get_shape(&time, judge_inner, click_effect_position(note, &self.render_queue), &note.note_id);
-										self.render_queue.append(&mut shapes);
-									}
-									judges.push(judge_inner.clone());
-									self.judged_note_id.push(notes[inner.note_id].note_id.clone());
-								}
-								judge.is_none()
+		// auto play and replays make clicks in the same way a player does
+		let areas: HashMap<String, Area> = self.judge_fields.iter().map(|(id, (field, _))| (id.clone(), field.area())).collect();
+		for (id, (_, judge_track)) in &mut self.judge_fields {
+			if let Some(notes) = self.notes.get(id) {
+				for click in &event.clicks {
+					// clicks carry their own time, so judging won't depend on frame rate
+					let context = JudgeContext {
+						time: click.time.unwrap_or(time),
+						windows: &windows,
+						areas: &areas,
+					};
+					judge_track.judge_tracks.retain_mut(|inner| {
+						if inner.linked_click != click.id {
+							return true;
+						}
+						let note = &notes[inner.note_id];
+						if let Some(judge) = rules.get(&note.judge_type).track(note, inner, click, &context) {
+							judged.push((note.clone(), judge, Some(inner.start_time - note.judge_time)));
+							false
+						}else {
+							true
+						}
+					});
+				}
+				// tracked notes may run out of time while their clicks send nothing
+				let context = JudgeContext {
+					time,
+					windows: &windows,
+					areas: &areas,
+				};
+				judge_track.judge_tracks.retain(|inner| {
+					let note = &notes[inner.note_id];
+					if let Some(judge) = rules.get(&note.judge_type).check_track(note, inner, &context) {
+						judged.push((note.clone(), judge, Some(inner.start_time - note.judge_time)));
+						false
+					}else {
+						true
+					}
+				});
+			}
+		}
+
+		// notes finished tracking just now shouldn't be started again
+		let finished: HashSet<String> = judged.iter().map(|(note, _, _)| note.note_id.clone()).collect();
+		// presses are owned by at most one note, and touches we are tracking can't press other notes.
+		let tracked_clicks: HashSet<usize> = self.judge_fields.values().flat_map(|(_, judge_track)| judge_track.judge_tracks.iter().map(|track| track.linked_click)).collect();
+		let mut candidates = vec!();
+		for (id, (_, judge_track)) in &self.judge_fields {
+			if let Some(notes) = self.notes.get(id) {
+				for (index, note) in notes.iter().enumerate().skip(judge_track.current_judge) {
+					if self.judged_note_id.contains(&note.note_id) || finished.contains(&note.note_id) || judge_track.judge_tracks.iter().any(|track| track.note_id == index) {
+						continue;
+					}
+					if time - note.judge_time < -windows.fade {
+						break;
+					}
+					if !rules.get(&note.judge_type).needs_press(note) {
+						continue;
+					}
+					for (click_index, click) in event.clicks.iter().enumerate() {
+						let click_delta = click.time.unwrap_or(time) - note.judge_time;
+						if is_owner(note, click) && matches!(click.state, ClickState::Pressed) && !tracked_clicks.contains(&click.id) && click_delta.abs() <= windows.fade && areas[id].is_point_inside(&click.position) {
+							candidates.push(PressCandidate {
+								click_index,
+								field_id: id.clone(),
+								note_index: index,
+								judge_time: note.judge_time,
+								click_delta,
 							});
 						}
-						for (id, note) in notes.iter_mut().enumerate() {
-							let delta = time - note.judge_time;
-							if delta < Duration::milliseconds(-150) {
-								break;
+					}
+				}
+			}
+		}
+		let selected = select_presses(candidates, &self.note_lock);
+		let consumed_clicks: HashSet<usize> = selected.iter().map(|candidate| candidate.click_index).collect();
+		let owned_notes: HashSet<(String, usize)> = selected.iter().map(|candidate| (candidate.field_id.clone(), candidate.note_index)).collect();
+		for candidate in selected {
+			let click = &event.clicks[candidate.click_index];
+			let note = &self.notes[&candidate.field_id][candidate.note_index];
+			let context = JudgeContext {
+				time: click.time.unwrap_or(time),
+				windows: &windows,
+				areas: &areas,
+			};
+			let action = rules.get(&note.judge_type).start(note, click, &context);
+			if let RuleAction::Ignore = action {
+				continue;
+			}
+			if let Some((_, judge_track)) = self.judge_fields.get_mut(&candidate.field_id) {
+				start_note(action, note, candidate.note_index, click, context.time, judge_track, &mut judged);
+			}
+		}
+
+		let finished: HashSet<String> = judged.iter().map(|(note, _, _)| note.note_id.clone()).collect();
+		for (id, (_, judge_track)) in &mut self.judge_fields {
+			if let Some(notes) = self.notes.get(id) {
+				for (index, note) in notes.iter().enumerate().skip(judge_track.current_judge) {
+					if self.judged_note_id.contains(&note.note_id) || finished.contains(&note.note_id) || owned_notes.contains(&(id.clone(), index)) || judge_track.judge_tracks.iter().any(|track| track.note_id == index) {
+						continue;
+					}
+					if time - note.judge_time < -windows.fade {
+						break;
+					}
+					let rule = rules.get(&note.judge_type);
+					let mut is_handled = false;
+					if !rule.needs_press(note) {
+						for (click_index, click) in event.clicks.iter().enumerate() {
+							if consumed_clicks.contains(&click_index) || !is_owner(note, click) {
+								continue;
 							}
-							if delta > Duration::milliseconds(150) {
-								judges.push(Judge::Miss);
-								self.judged_note_id.push(note.note_id.to_string());
-								if show_click_effect {
-									let mut shapes = match self.click_effects.get(&note.click_effect_id) {
-										Some(t) => t.clone(),
-										None => Default::default()
-									}.get_shape(&time, &Judge::Miss, click_effect_position(note, &self.render_queue), &note.note_id);
-									self.render_queue.append(&mut shapes);
-								}
-								judge_track.current_judge += 1;
+							let context = JudgeContext {
+								time: click.time.unwrap_or(time),
+								windows: &windows,
+								areas: &areas,
+							};
+							if (context.time - note.judge_time).abs() > windows.fade || !areas[id].is_point_inside(&click.position) {
 								continue;
 							}
-							for click in &event.clicks {
-								let area = Area::new(field.inner.min, field.inner.max).transform(&Style {
-									position: field.inner.position,
-									size: field.inner.scale,
-									rotate: field.inner.rotate,
-									transform_origin: field.inner.transform_origin,
-									..Default::default()
-								});
-								if area.is_point_inside(&click.position) { 
-									let track = JudgeTrack {
-										note_id: id,
-										linked_click: click.id,
-										start_time: time,
-										last_position: click.position,
-									};
-									match (&click.state, &note.judge_type) {
-										(ClickState::Pressed, JudgeType::Hold(_)) | 
-										(ClickState::Pressed, JudgeType::TapAndFlick) | 
-										(ClickState::Pressed, JudgeType::TapChain(_)) | 
-										(ClickState::Pressed, JudgeType::AngledTapFilck(_)) => {
-											judge_track.judge_tracks.push(track);
-											judge_track.current_judge += 1;
-										},
-										(_, JudgeType::Flick) | (_, JudgeType::Chain(_)) | (_, JudgeType::AngledFilck(_)) => {
-											judge_track.judge_tracks.push(track);
-											judge_track.current_judge += 1;
-										},
-										(ClickState::Pressed, JudgeType::Tap) => {
-											let judge;
-											let delta = delta.abs();
-											if delta < Duration::milliseconds(50) {
-												judge = Judge::Immaculate((delta / Duration::milliseconds(50)) as f32);
-											}else if delta < Duration::milliseconds(70) {
-												judge = Judge::Extra
-											}else if delta < Duration::milliseconds(120) {
-												judge = Judge::Normal
-											}else if delta < Duration::milliseconds(150) {
-												judge = Judge::Fade;
-											}else {
-												judge = Judge::Miss;
-											}
-
-											judge_track.current_judge += 1;
-											if show_click_effect {
-												let mut shapes = match self.click_effects.get(&note.click_effect_id) {
-													Some(t) => t.clone(),
-													None => Default::default()
-												}.get_shape(&time, &judge, click_effect_position(note, &self.render_queue), &note.note_id);
-												self.render_queue.append(&mut shapes);
-											}
-											self.judged_note_id.push(note.note_id.to_string());
-											judges.push(judge);
-										},
-										(_, JudgeType::Slide) => {
-											let judge = Judge::Immaculate(1.0);
-
-											judge_track.current_judge += 1;
-											if show_click_effect {
-												let mut shapes = match self.click_effects.get(&note.click_effect_id) {
-													Some(t) => t.clone(),
-													None => Default::default()
-												}.get_shape(&time, &judge, click_effect_position(note, &self.render_queue), &note.note_id);
-												self.render_queue.append(&mut shapes);
-											}
-											self.judged_note_id.push(note.note_id.to_string());
-											judges.push(judge);
-										},
-										_ => {},
-									}
-								}
+							let action = rule.start(note, click, &context);
+							if let RuleAction::Ignore = action {
+								continue;
 							}
+							start_note(action, note, index, click, context.time, judge_track, &mut judged);
+							is_handled = true;
+							break;
 						}
 					}
-				}
-			},
-			PlayMode::Auto => {
-				for (id, (_, judge_track)) in &mut self.judge_fields {
-					if let Some(notes) = self.notes.get_mut(id) {
-						for note in notes {
-							if note.judge_time < time {
-								let judge = Judge::Immaculate(1.0);
-								judge_track.current_judge += 1;
-								if show_click_effect {
-									let mut shapes = match self.click_effects.get(&note.click_effect_id) {
-										Some(t) => t.clone(),
-										None => Default::default()
-									}.get_shape(&time, &judge, click_effect_position(note, &self.render_queue), &note.note_id);
-									self.render_queue.append(&mut shapes);
-								}
-								self.judged_note_id.push(note.note_id.to_string());
-								judges.push(judge);
-							}
-						}
+					// checks after clicks, a click inside window may arrive later than the window closes
+					let context = JudgeContext {
+						time,
+						windows: &windows,
+						areas: &areas,
+					};
+					if !is_handled && rule.is_missed(note, &context) {
+						judged.push((note.clone(), Judge::Miss, None));
 					}
 				}
-			},
-			PlayMode::Replay(_) => todo!()
+			}
 		}
-		for judge in judges {
+
+		for (note, judge, offset) in judged {
+			if show_click_effect {
+				let mut shapes = match self.click_effects.get(&note.click_effect_id) {
+					Some(t) => t.clone(),
+					None => Default::default()
+				}.get_shape(&time, &judge, click_effect_position(&note, &self.render_queue), &note.note_id);
+				self.render_queue.append(&mut shapes);
+			}
 			self.play_click_sound()?;
-			self.caculate(judge);
+			self.caculate(JudgeRecord {
+				// a miss has no input to measure
+				offset: if let Judge::Miss = judge { None } else { offset },
+				note_id: note.note_id.clone(),
+				judge,
+			});
+			self.judged_note_id.insert(note.note_id);
+		}
+		for (id, (_, judge_track)) in &mut self.judge_fields {
+			if let Some(notes) = self.notes.get(id) {
+				while notes.get(judge_track.current_judge).is_some_and(|note| self.judged_note_id.contains(&note.note_id)) {
+					judge_track.current_judge += 1;
+				}
+			}
 		}
 		Ok(())
 	}
@@ -1344,7 +2095,8 @@ impl PlayInfo {
 	}
 
 	#[inline]
-	fn caculate(&mut self, judge: Judge) {
+	fn caculate(&mut self, record: JudgeRecord) {
+		let judge = &record.judge;
 		self.judged_notes += 1;
 		match judge {
 			Judge::Fade | Judge::Miss => self.combo = 0,
@@ -1354,16 +2106,357 @@ impl PlayInfo {
 			self.max_combo = self.combo;
 		}
 
-		let acc = match judge {
-			Judge::Immaculate(inner) => 0.8 + inner * 0.2,
-			Judge::Extra => 0.7,
-			Judge::Normal => 0.3,
-			Judge::Fade => 0.1,
-			Judge::Miss => 0.0
-		};
+		let acc = self.scoring.accuracy_of(judge);
 		self.accuracy = (self.accuracy * (self.judged_notes - 1) as f32 + acc) / self.judged_notes as f32;
-		self.score = self.accuracy * self.judged_notes as f32 / self.total_notes as f32 * 2.0 * 1e7 * 0.9 + self.max_combo as f32 / self.total_notes as f32 * 0.1 * 2.0 * 1e7;
-		self.judge_vec.push(judge);
+		// ex score only counts judges, so modifiers don't change it
+		let multiplier = match self.scoring {
+			ScoringSystem::ExScore => 1.0,
+			_ => Modifier::total_multiplier(&self.modifiers),
+		};
+		self.score = self.scoring.score(&ScoreProgress {
+			accuracy: self.accuracy,
+			judged_notes: self.judged_notes,
+			total_notes: self.total_notes,
+			combo: self.combo,
+			max_combo: self.max_combo,
+		}) * multiplier;
+		self.score_history.push(self.score);
+		self.life = (self.life + self.life_gauge.change_of(judge)).clamp(0.0, 1.0);
+		// practices never fail
+		if self.life <= 0.0 && self.life_gauge.can_fail && !self.is_practice {
+			self.is_failed = true;
+		}
+		if let Some(offset) = record.offset {
+			self.timing_histogram.push(offset);
+			if offset < Duration::ZERO {
+				self.fast_count += 1;
+			}else if offset > Duration::ZERO {
+				self.late_count += 1;
+			}
+		}
+		self.judge_vec.push(record);
+	}
+
+	/// average timing offset of all judgements with input, negative means early. None if nothing is hit yet.
+	pub fn timing_mean(&self) -> Option<Duration> {
+		let offsets: Vec<Duration> = self.judge_vec.iter().filter_map(|record| record.offset).collect();
+		if offsets.is_empty() {
+			return None;
+		}
+		Some(offsets.iter().sum::<Duration>() / offsets.len() as f32)
+	}
+
+	/// standard deviation of timing offsets, None if nothing is hit yet.
+	pub fn timing_deviation(&self) -> Option<Duration> {
+		let mean = self.timing_mean()?.as_seconds_f32();
+		let offsets: Vec<f32> = self.judge_vec.iter().filter_map(|record| record.offset).map(|offset| offset.as_seconds_f32()).collect();
+		let variance = offsets.iter().map(|offset| (offset - mean).powi(2)).sum::<f32>() / offsets.len() as f32;
+		Some(Duration::seconds_f32(variance.sqrt()))
+	}
+}
+
+/// a press that may be owned by a note
+#[derive(Clone, Debug, PartialEq)]
+struct PressCandidate {
+	click_index: usize,
+	field_id: String,
+	note_index: usize,
+	judge_time: Duration,
+	click_delta: Duration,
+}
+
+/// decide which note each press goes to, see [`NoteLock`]. returns candidates that should start their note.
+fn select_presses(mut candidates: Vec<PressCandidate>, note_lock: &NoteLock) -> Vec<PressCandidate> {
+	match note_lock {
+		NoteLock::Closest => candidates.sort_by_key(|candidate| candidate.click_delta.abs()),
+		NoteLock::Earliest => {
+			// a press can only go to the earliest note it reaches, even if that note is taken by another press.
+			let mut earliest: HashMap<usize, Duration> = HashMap::new();
+			for candidate in &candidates {
+				let time = earliest.entry(candidate.click_index).or_insert(candidate.judge_time);
+				*time = (*time).min(candidate.judge_time);
+			}
+			candidates.retain(|candidate| candidate.judge_time <= earliest[&candidate.click_index]);
+			candidates.sort_by_key(|candidate| (candidate.judge_time, candidate.click_delta.abs()));
+		},
+		NoteLock::Off => {},
+	}
+	let mut consumed_clicks = HashSet::new();
+	let mut owned_notes = HashSet::new();
+	candidates.retain(|candidate| {
+		if owned_notes.contains(&(candidate.field_id.clone(), candidate.note_index)) {
+			return false
+		}
+		if *note_lock != NoteLock::Off && consumed_clicks.contains(&candidate.click_index) {
+			return false
+		}
+		consumed_clicks.insert(candidate.click_index);
+		owned_notes.insert((candidate.field_id.clone(), candidate.note_index));
+		true
+	});
+	candidates
+}
+
+/// clicks a perfect player makes for a note, `press` for whether the note needs a press to start.
+///
+/// no release is made, since a released touch may start other notes by accident.
+fn auto_clicks_of(note: &Note, press: bool, windows: &JudgeWindows, id: usize) -> Vec<AutoClick> {
+	let click = |time: Duration, field_id: &str, offset: Vec2, state: ClickState| AutoClick {
+		id,
+		time,
+		field_id: field_id.to_string(),
+		offset,
+		state,
+	};
+	let flick = |angle: f32| Vec2::new(angle.cos(), angle.sin()) * windows.flick_distance * 2.0;
+	let mut clicks = vec![click(note.judge_time, &note.judge_field_id, Vec2::ZERO, if press {
+		ClickState::Pressed
+	}else {
+		ClickState::Pressing
+	})];
+	match &note.judge_type {
+		JudgeType::Flick | JudgeType::TapAndFlick => clicks.push(click(note.judge_time + AUTO_STEP, &note.judge_field_id, flick(-std::f32::consts::FRAC_PI_2), ClickState::Pressing)),
+		JudgeType::AngledFilck(angle) | JudgeType::AngledTapFilck(angle) => clicks.push(click(note.judge_time + AUTO_STEP, &note.judge_field_id, flick(*angle), ClickState::Pressing)),
+		JudgeType::Hold(sustain) => clicks.push(click(note.judge_time + *sustain + AUTO_STEP, &note.judge_field_id, Vec2::ZERO, ClickState::Pressing)),
+		JudgeType::Chain(steps) | JudgeType::TapChain(steps) => {
+			let mut elapsed = Duration::ZERO;
+			let mut last = note.judge_time;
+			for (field_id, sustain) in steps {
+				elapsed += *sustain;
+				last = (note.judge_time + elapsed).max(last + AUTO_STEP);
+				clicks.push(click(last, field_id, Vec2::ZERO, ClickState::Pressing));
+			}
+		},
+		_ => {},
+	}
+	clicks
+}
+
+/// apply what a rule decides when a click starts a note
+fn start_note(action: RuleAction, note: &Note, note_index: usize, click: &Click, click_time: Duration, judge_track: &mut JudgeInfo, judged: &mut Vec<(Note, Judge, Option<Duration>)>) {
+	match action {
+		RuleAction::Ignore => {},
+		RuleAction::Track => judge_track.judge_tracks.push(JudgeTrack {
+			note_id: note_index,
+			linked_click: click.id,
+			start_time: click_time,
+			last_position: click.position,
+			chain_progress: 0,
+		}),
+		RuleAction::Judge(judge) => judged.push((note.clone(), judge, Some(click_time - note.judge_time))),
+	}
+}
+
+impl JudgeCounts {
+	/// count a judgement in
+	pub fn push(&mut self, judge: &Judge) {
+		match judge {
+			Judge::Immaculate(_) => self.immaculate += 1,
+			Judge::Extra => self.extra += 1,
+			Judge::Normal => self.normal += 1,
+			Judge::Fade => self.fade += 1,
+			Judge::Miss => self.miss += 1,
+		}
+	}
+}
+
+impl GradeThreshold {
+	/// grades used by default
+	pub fn default_grades() -> Vec<Self> {
+		[("S+", 0.99), ("S", 0.95), ("A", 0.9), ("B", 0.8), ("C", 0.7), ("D", 0.0)].into_iter().map(|(grade, min_score)| Self {
+			grade: grade.to_string(),
+			min_score,
+		}).collect()
+	}
+
+	/// grade of a score ratio, empty if no threshold is reached.
+	pub fn grade_of(grades: &[Self], score: f32) -> String {
+		grades.iter().find(|threshold| score >= threshold.min_score).map(|threshold| threshold.grade.clone()).unwrap_or_default()
+	}
+}
+
+impl Chart {
+	/// hash of everything in this chart as hex, stays the same between runs and platforms.
+	pub fn content_hash(&self) -> Result<String, Error> {
+		// tables of toml values are sorted, so order of hash maps doesn't matter
+		let content = toml::Value::try_from(self)?.to_string();
+		Ok(content_hash(content.as_bytes()))
+	}
+}
+
+impl ChartInfo {
+	/// tells which chart this is
+	pub fn identity(&self) -> ChartIdentity {
+		ChartIdentity {
+			song_name: self.song_name.clone(),
+			charter: self.charter.clone(),
+			version: self.version.clone(),
+		}
+	}
+}
+
+impl PlayHistory {
+	/// plays of given chart, latest first.
+	pub fn plays_of<'a>(&'a self, chart: &'a ChartIdentity) -> impl Iterator<Item = &'a PlayRecord> {
+		self.records.iter().rev().filter(move |record| &record.chart == chart)
+	}
+
+	/// last `count` plays of given chart, latest first.
+	pub fn last_plays(&self, chart: &ChartIdentity, count: usize) -> Vec<&PlayRecord> {
+		self.plays_of(chart).take(count).collect()
+	}
+
+	/// play with highest score of given chart, only plays counted with `scoring` are compared.
+	pub fn best_play(&self, chart: &ChartIdentity, scoring: &ScoringSystem) -> Option<&PlayRecord> {
+		self.plays_of(chart).filter(|record| &record.result.scoring == scoring).max_by(|a, b| a.result.score.total_cmp(&b.result.score))
+	}
+
+	/// every play finished after `date`, oldest first.
+	pub fn plays_since(&self, date: OffsetDateTime) -> Vec<&PlayRecord> {
+		self.records.iter().filter(|record| record.date >= date).collect()
+	}
+
+	/// rebuild [`ChartHistory`] of given chart, None if it has never been played with `scoring`.
+	pub fn chart_history(&self, chart: &ChartIdentity, scoring: &ScoringSystem) -> Option<ChartHistory> {
+		let mut history = ChartHistory {
+			scoring: scoring.clone(),
+			..Default::default()
+		};
+		for record in self.plays_of(chart) {
+			history.merge(&record.result);
+		}
+		if history.play_count == 0 {
+			None
+		}else {
+			Some(history)
+		}
+	}
+}
+
+impl ChartHistory {
+	/// merge a result in, returns false if it is counted with another scoring system.
+	pub fn merge(&mut self, result: &PlayResult) -> bool {
+		if self.scoring != result.scoring {
+			return false;
+		}
+		if self.play_count == 0 || result.score as usize >= self.high_score {
+			self.high_score = result.score as usize;
+			self.best_grade = Some(result.grade.clone());
+		}
+		self.high_accurcy = self.high_accurcy.max(result.accuracy);
+		self.best_clear = self.best_clear.clone().max(Some(result.clear_type.clone()));
+		self.play_count += 1;
+		true
+	}
+}
+
+impl TimingHistogram {
+	/// create a histogram covers offsets from `-range` to `range`
+	pub fn new(range: Duration, bucket_width: Duration) -> Self {
+		let count = ((range * 2) / bucket_width).ceil().max(1.0) as usize;
+		Self {
+			start: -range,
+			bucket_width,
+			buckets: vec![0; count],
+			underflow: 0,
+			overflow: 0,
+		}
+	}
+
+	/// count an offset in, offsets out of range will be counted in [`TimingHistogram::underflow`] or [`TimingHistogram::overflow`].
+	pub fn push(&mut self, offset: Duration) {
+		let index = ((offset - self.start) / self.bucket_width).floor();
+		if index < 0.0 {
+			self.underflow += 1;
+		}else if index as usize >= self.buckets.len() {
+			self.overflow += 1;
+		}else {
+			self.buckets[index as usize] += 1;
+		}
+	}
+
+	/// reset every bucket to zero
+	pub fn clear(&mut self) {
+		for bucket in &mut self.buckets {
+			*bucket = 0;
+		}
+		self.underflow = 0;
+		self.overflow = 0;
+	}
+}
+
+impl LifeGaugeProfile {
+	/// get the gauge this profile stands for
+	pub fn gauge(&self) -> LifeGauge {
+		match self {
+			Self::NoFail => LifeGauge {
+				can_fail: false,
+				..Default::default()
+			},
+			Self::Standard => LifeGauge::default(),
+			Self::SuddenDeath => LifeGauge {
+				initial: 1.0,
+				immaculate: 0.0,
+				extra: 0.0,
+				normal: 0.0,
+				fade: 0.0,
+				miss: -1.0,
+				can_fail: true,
+			},
+			Self::Custom(inner) => inner.clone(),
+		}
+	}
+}
+
+impl LifeGauge {
+	/// how much life does a judgement give
+	pub fn change_of(&self, judge: &Judge) -> f32 {
+		match judge {
+			Judge::Immaculate(_) => self.immaculate,
+			Judge::Extra => self.extra,
+			Judge::Normal => self.normal,
+			Judge::Fade => self.fade,
+			Judge::Miss => self.miss,
+		}
+	}
+}
+
+impl JudgeWindowProfile {
+	/// get the windows this profile stands for
+	pub fn windows(&self) -> JudgeWindows {
+		match self {
+			Self::Lenient => JudgeWindows {
+				immaculate: Duration::milliseconds(70),
+				extra: Duration::milliseconds(100),
+				normal: Duration::milliseconds(160),
+				fade: Duration::milliseconds(200),
+				flick_timeout: Duration::milliseconds(160),
+				flick_distance: 3.0,
+			},
+			Self::Standard => JudgeWindows::default(),
+			Self::Strict => JudgeWindows {
+				immaculate: Duration::milliseconds(35),
+				extra: Duration::milliseconds(50),
+				normal: Duration::milliseconds(90),
+				fade: Duration::milliseconds(120),
+				flick_timeout: Duration::milliseconds(100),
+				flick_distance: 8.0,
+			},
+			Self::Custom(windows) => windows.clone(),
+		}
+	}
+}
+
+impl JudgeField {
+	/// where does this judge field take, transform included.
+	pub fn area(&self) -> Area {
+		Area::new(self.inner.min, self.inner.max).transform(&Style {
+			position: self.inner.position,
+			size: self.inner.scale,
+			rotate: self.inner.rotate,
+			transform_origin: self.inner.transform_origin,
+			..Default::default()
+		})
 	}
 }
 
@@ -1472,6 +2565,63 @@ fn process_chart_path(assets_path: &str) -> Result<Vec<ChartInfo>, Error> {
 	Ok(charts)
 }
 
+#[allow(dead_code)]
+fn process_chart_history(assets_path: &str, charts: &mut [ChartInfo]) -> Result<(), Error> {
+	info!("processing chart history");
+	let history_path = format!("{}/shapoist_assets/user/chart_history.toml", assets_path);
+	let mut histories: HashMap<String, ChartHistory> = match read_file_to_string(&history_path) {
+		Ok(t) => match parse_toml(&t) {
+			Ok(histories) => histories,
+			Err(e) => {
+				warn!("chart history can't be parsed, using histories in chart configs. info: {}", e);
+				return Ok(());
+			}
+		},
+		Err(IoError(t)) => {
+			if let std::io::ErrorKind::NotFound = t.kind() {
+				return Ok(());
+			}else {
+				return Err(t.into());
+			}
+		},
+		Err(e) => return Err(e),
+	};
+	// histories saved by core overrides the ones in chart configs
+	for chart in charts {
+		if let Some(history) = chart.folder_name().and_then(|name| histories.remove(&name)) {
+			chart.history = Some(history);
+		}
+	}
+	Ok(())
+}
+
+#[allow(dead_code)]
+fn process_play_history(assets_path: &str) -> Result<PlayHistory, Error> {
+	info!("processing play history");
+	let history_path = format!("{}/shapoist_assets/user/history.toml", assets_path);
+	match read_file_to_string(&history_path) {
+		Ok(t) => match parse_toml(&t) {
+			Ok(history) => Ok(history),
+			Err(e) => {
+				// keeps the broken file, so next play won't append to it
+				let broken_path = format!("{}.broken", history_path);
+				warn!("play history can't be parsed, moving it to {} and starting a new one. info: {}", broken_path, e);
+				copy_file(&history_path, &broken_path)?;
+				remove_file(&history_path)?;
+				Ok(PlayHistory::default())
+			}
+		},
+		Err(IoError(t)) => {
+			if let std::io::ErrorKind::NotFound = t.kind() {
+				Ok(PlayHistory::default())
+			}else {
+				Err(t.into())
+			}
+		},
+		Err(e) => Err(e),
+	}
+}
+
 #[allow(dead_code)]
 fn process_script_path(assets_path: &str) -> Result<Vec<ScriptInfo>, Error> {
 	info!("processing script path");
@@ -1519,6 +2669,29 @@ fn unzip(path: &str, uzip_path: &str) -> Result<(), Error> {
 	Ok(())
 }
 
+/// a replay file is a zip of its header and the replay itself, so listing replays only reads headers.
+fn write_replay<P: AsRef<Path>>(path: P, header: &ReplayHeader, replay: &Replay) -> Result<(), Error> {
+	let options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+	let mut zip = zip::ZipWriter::new(std::io::Cursor::new(vec!()));
+	zip.start_file(REPLAY_HEADER, options)?;
+	zip.write_all(to_toml(header)?.as_bytes())?;
+	zip.start_file(REPLAY_DATA, options)?;
+	zip.write_all(to_toml(replay)?.as_bytes())?;
+	let bytes = zip.finish()?.into_inner();
+	if !path.as_ref().exists() {
+		create_file(&path)?;
+	}
+	write_file(path, bytes.as_slice())
+}
+
+fn read_replay_entry<T: for<'a> serde::Deserialize<'a>>(path: impl AsRef<Path>, name: &str) -> Result<T, Error> {
+	let mut zip = zip::ZipArchive::new(read_file(path)?)?;
+	let mut file = zip.by_name(name)?;
+	let mut content = String::new();
+	file.read_to_string(&mut content)?;
+	parse_toml(&content)
+}
+
 impl Default for ShapoistCore {
 	fn default() -> Self {
 		Self {
@@ -1539,6 +2712,194 @@ impl Default for ShapoistCore {
 			current_sound: None,
 			adjustment: vec!(),
 			is_in_delay_adjustment: false,
+			track_cache: HashMap::new(),
+			judge_rules: JudgeRules::default(),
+			play_history: PlayHistory::default(),
+		}
+	}
+}
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn candidate(click_index: usize, note_index: usize, judge_time: i64, click_time: i64) -> PressCandidate {
+		PressCandidate {
+			click_index,
+			field_id: "field".into(),
+			note_index,
+			judge_time: Duration::milliseconds(judge_time),
+			click_delta: Duration::milliseconds(click_time - judge_time),
+		}
+	}
+
+	fn selected(candidates: Vec<PressCandidate>, note_lock: NoteLock) -> Vec<(usize, usize)> {
+		let mut output: Vec<(usize, usize)> = select_presses(candidates, &note_lock).iter().map(|candidate| (candidate.click_index, candidate.note_index)).collect();
+		output.sort();
+		output
+	}
+
+	#[test]
+	fn closest_lock_picks_nearest_note() {
+		// one press at 1090ms, between notes at 1000ms and 1100ms
+		let candidates = vec!(candidate(0, 0, 1000, 1090), candidate(0, 1, 1100, 1090));
+		assert_eq!(selected(candidates, NoteLock::Closest), vec!((0, 1)));
+	}
+
+	#[test]
+	fn earliest_lock_picks_earliest_note() {
+		let candidates = vec!(candidate(0, 0, 1000, 1090), candidate(0, 1, 1100, 1090));
+		assert_eq!(selected(candidates, NoteLock::Earliest), vec!((0, 0)));
+	}
+
+	#[test]
+	fn earliest_lock_blocks_later_notes() {
+		// both presses reach the first note, the second press can't skip ahead to the later note.
+		let candidates = vec!(candidate(0, 0, 1000, 1000), candidate(1, 0, 1000, 1090), candidate(1, 1, 1100, 1090));
+		assert_eq!(selected(candidates, NoteLock::Earliest), vec!((0, 0)));
+		// while closest lets it take the later one
+		let candidates = vec!(candidate(0, 0, 1000, 1000), candidate(1, 0, 1000, 1090), candidate(1, 1, 1100, 1090));
+		assert_eq!(selected(candidates, NoteLock::Closest), vec!((0, 0), (1, 1)));
+	}
+
+	#[test]
+	fn presses_own_one_note_each() {
+		let candidates = vec!(candidate(0, 0, 1000, 1000), candidate(1, 0, 1000, 1010));
+		assert_eq!(selected(candidates, NoteLock::Closest), vec!((0, 0)));
+	}
+
+	#[test]
+	fn no_lock_judges_every_reached_note() {
+		let candidates = vec!(candidate(0, 0, 1000, 1050), candidate(0, 1, 1100, 1050));
+		assert_eq!(selected(candidates, NoteLock::Off), vec!((0, 0), (0, 1)));
+	}
+
+	fn tap_chart(times: &[i64]) -> (Chart, ChartInfo) {
+		let chart = Chart {
+			notes: times.iter().enumerate().map(|(index, time)| (format!("note{}", index), Note {
+				note_id: format!("note{}", index),
+				judge_type: JudgeType::Tap,
+				judge_time: Duration::milliseconds(*time),
+				judge_field_id: "default".into(),
+				..Default::default()
+			})).collect(),
+			shapes: HashMap::new(),
+			..Default::default()
+		};
+		let info = ChartInfo {
+			sustain_time: Duration::seconds(10),
+			..Default::default()
+		};
+		(chart, info)
+	}
+
+	fn tap(id: usize, time: i64) -> JudgeEvent {
+		JudgeEvent {
+			clicks: vec!(Click {
+				id,
+				position: Vec2::same(300.0),
+				state: ClickState::Pressed,
+				key: None,
+				time: Some(Duration::milliseconds(time)),
+			}),
+			time: Some(Duration::milliseconds(time)),
+			..Default::default()
 		}
 	}
-}
\ No newline at end of file
+
+	fn watch(chart: &Chart, info: &ChartInfo, replay: Replay) -> PlayInfo {
+		let settings = Settings::default();
+		let rules = JudgeRules::default();
+		let mut play_info = PlayInfo::new(chart, info, PlayMode::Replay(replay), vec!(), &settings, Duration::ZERO, None).unwrap();
+		for time in (0..=3000).step_by(16) {
+			play_info.play_replay(Duration::milliseconds(time), false, &settings, &rules).unwrap();
+		}
+		play_info
+	}
+
+	#[test]
+	fn replay_is_judged_through_judge_pipeline() {
+		let (chart, info) = tap_chart(&[1000, 1500, 2000]);
+		let play_info = watch(&chart, &info, Replay {
+			judge_history_events: vec!(tap(0, 1000), tap(1, 1510), tap(2, 1990)),
+			..Default::default()
+		});
+		let judged: Vec<&str> = play_info.judge_vec.iter().map(|record| record.note_id.as_str()).collect();
+		assert_eq!(judged, vec!("note0", "note1", "note2"));
+		assert_eq!(play_info.judge_vec[0].judge, Judge::Immaculate(1.0));
+		assert_eq!(play_info.score_history.len(), 3);
+	}
+
+	#[test]
+	fn watching_replay_again_gives_same_scores() {
+		let (chart, info) = tap_chart(&[1000, 1500, 2000]);
+		let mut replay = Replay {
+			judge_history_events: vec!(tap(0, 1000), tap(1, 1510), tap(2, 1990)),
+			..Default::default()
+		};
+		replay.score_history = watch(&chart, &info, replay.clone()).score_history;
+		let play_info = watch(&chart, &info, replay.clone());
+		assert_eq!(play_info.score_history, replay.score_history);
+		assert_eq!(play_info.replay_divergence(), None);
+		// a replay recorded with other scores is found
+		replay.score_history[1] += 1000.0;
+		assert_eq!(watch(&chart, &info, replay).replay_divergence(), Some(1));
+	}
+
+	#[test]
+	fn recorded_play_is_replayed_the_same() {
+		let (chart, info) = tap_chart(&[1000, 1500, 2000]);
+		let settings = Settings::default();
+		let rules = JudgeRules::default();
+		let mut play_info = PlayInfo::new(&chart, &info, PlayMode::Normal, vec!(), &settings, Duration::ZERO, None).unwrap();
+		for (id, time) in [(0, 1000), (1, 1510), (2, 1990)] {
+			let event = play_info.record_event(tap(id, time), Duration::milliseconds(time), &settings.key_bindings);
+			play_info.judge(event, Duration::milliseconds(time), false, &settings, &rules).unwrap();
+		}
+		let replay = play_info.summarize(Duration::seconds(3), &settings.grades).replay.unwrap();
+		assert_eq!(replay.judge_history_events.len(), 3);
+		let watched = watch(&chart, &info, replay.clone());
+		assert_eq!(watched.score_history, replay.score_history);
+		assert_eq!(watched.replay_divergence(), None);
+	}
+
+	#[test]
+	fn content_hash_is_stable() {
+		// FNV-1a of empty content and "a"
+		assert_eq!(content_hash(b""), "cbf29ce484222325");
+		assert_eq!(content_hash(b"a"), "af63dc4c8601ec8c");
+		let (chart, _) = tap_chart(&[1000, 1500, 2000, 2500]);
+		// same notes inserted in another order
+		let mut reordered = chart.clone();
+		let mut notes: Vec<(String, Note)> = chart.notes.clone().into_iter().collect();
+		notes.reverse();
+		reordered.notes = notes.into_iter().collect();
+		assert_eq!(chart.content_hash().unwrap(), reordered.content_hash().unwrap());
+		assert_eq!(chart.content_hash().unwrap(), chart.clone().content_hash().unwrap());
+		let (moved, _) = tap_chart(&[1000, 1500, 2000, 2510]);
+		assert_ne!(chart.content_hash().unwrap(), moved.content_hash().unwrap());
+	}
+
+	#[test]
+	fn saved_replay_plays_back_the_same() {
+		let (chart, info) = tap_chart(&[1000, 1500, 2000]);
+		let mut replay = Replay {
+			judge_history_events: vec!(tap(0, 1000), tap(1, 1510), tap(2, 1990)),
+			..Default::default()
+		};
+		replay.score_history = watch(&chart, &info, replay.clone()).score_history;
+		let header = ReplayHeader {
+			chart: info.identity(),
+			chart_hash: chart.content_hash().unwrap(),
+			core_version: env!("CARGO_PKG_VERSION").into(),
+			..Default::default()
+		};
+		let path = std::env::temp_dir().join(format!("shapoist_replay_test_{}.{}", std::process::id(), REPLAY_EXTENSION));
+		write_replay(&path, &header, &replay).unwrap();
+		let loaded_header: ReplayHeader = read_replay_entry(&path, REPLAY_HEADER).unwrap();
+		let loaded: Replay = read_replay_entry(&path, REPLAY_DATA).unwrap();
+		let _ = std::fs::remove_file(&path);
+		assert_eq!(loaded_header, header);
+		assert_eq!(loaded.judge_history_events.len(), replay.judge_history_events.len());
+		assert_eq!(watch(&chart, &info, loaded).replay_divergence(), None);
+	}
+}
@@ -13,9 +13,15 @@ use nablo_shape::prelude::shape_elements::Rect;
 use nablo_shape::prelude::*;
 use nablo_data::CanBeAnimated;
 use time::Duration;
+use time::OffsetDateTime;
 use shapoist_request::prelude::*;
 use std::thread::JoinHandle;
 use crate::system::command::Command;
+use crate::system::audio_analysis::TrackAnalysis;
+use crate::system::chart_analysis::ChartStats;
+use crate::system::judge_rule::JudgeRules;
+use crate::system::modifier::Modifier;
+use crate::system::scoring::ScoringSystem;
 use kira::manager::AudioManager;
 use crate::system::timer::Timer;
 use nablo_shape::math::Area;
@@ -34,6 +40,8 @@ const EXTRA_COLOR: [u8; 4] = [67, 187, 252, 255];
 const NORMAL_COLOR: [u8; 4] = [21, 223, 112, 255];
 const FADE_COLOR: [u8; 4] = [107, 55, 34, 255];
 const MISS_COLOR: [u8; 4] = [255, 255, 255, 255];
+/// how long do we wait before chart starts if neither chart nor [`Settings`] says.
+pub const DEFAULT_LEAD_IN: Duration = Duration::seconds(3);
 
 /// The core part of shapoist.
 ///
@@ -70,6 +78,12 @@ pub struct ShapoistCore {
 	pub(crate) thread_pool: Vec<JoinHandle<Result<(), ClientError>>>,
 	pub(crate) current_sound: Option<StaticSoundData>, 
 	pub(crate) adjustment: Vec<Duration>,
+	/// decoded tracks, keyed by chart path
+	pub(crate) track_cache: HashMap<PathBuf, TrackAnalysis>,
+	/// rules of custom note types, see [`ShapoistCore::register_judge_rule()`]
+	pub judge_rules: JudgeRules,
+	/// every completed play, saved in `shapoist_assets/user/history.toml`
+	pub play_history: PlayHistory,
 }
 
 #[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq, Default)]
@@ -83,7 +97,7 @@ pub enum Varible {
 	#[default] None
 }
 
-#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq, Default)]
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, Default)]
 #[serde(default)]
 /// saves detailed information for [`Chart`]
 pub struct ChartInfo {
@@ -114,9 +128,48 @@ pub struct ChartInfo {
 	#[serde(serialize_with = "serialize_duration")]
 	#[serde(deserialize_with = "deserialize_duration")]
 	pub offset: Duration,
+	/// how long do we wait before chart starts, None for [`DEFAULT_LEAD_IN`]. can be overrided by [`Settings::lead_in`]
+	#[serde(skip_serializing_if = "Option::is_none")]
+	#[serde(serialize_with = "serialize_option_duration")]
+	#[serde(deserialize_with = "deserialize_option_duration")]
+	pub lead_in: Option<Duration>,
+	/// which judge windows does this chart use, None for [`JudgeWindowProfile::Standard`]. can be overrided by [`Settings::judge_windows`]
+	pub judge_windows: Option<JudgeWindowProfile>,
+	/// how is this chart scored, None for [`ScoringSystem::Standard`]. can be overrided by [`Settings::scoring`]
+	pub scoring: Option<ScoringSystem>,
+	/// computed when chart is read or indexed, see [`ShapoistCore::index_chart_stats()`]
+	#[serde(skip)]
+	pub stats: Option<ChartStats>,
+}
+
+impl PartialEq for ChartInfo {
+	// stats are only a cache of the chart, they don't make charts different
+	fn eq(&self, other: &Self) -> bool {
+		self.song_name == other.song_name &&
+		self.bpm == other.bpm &&
+		self.diffculty == other.diffculty &&
+		self.producer == other.producer &&
+		self.charter == other.charter &&
+		self.artist == other.artist &&
+		self.version == other.version &&
+		self.path == other.path &&
+		self.image_size == other.image_size &&
+		self.needed_scrpt == other.needed_scrpt &&
+		self.used_script == other.used_script &&
+		self.label == other.label &&
+		self.publish_info == other.publish_info &&
+		self.condition == other.condition &&
+		self.sustain_time == other.sustain_time &&
+		self.chart_varibles == other.chart_varibles &&
+		self.history == other.history &&
+		self.offset == other.offset &&
+		self.lead_in == other.lead_in &&
+		self.judge_windows == other.judge_windows &&
+		self.scoring == other.scoring
+	}
 }
 
-fn serialize_duration<S>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> 
+pub(crate) fn serialize_duration<S>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> 
 	where S: Serializer 
 {
 	let mut serde_state = serde::Serializer::serialize_struct(serializer, "Duration", 1)?;
@@ -130,13 +183,29 @@ struct DeDuration {
 	time: f32,
 }
 
-fn deserialize_duration<'de, D>(deserializer: D) -> Result<Duration, D::Error> 
+pub(crate) fn deserialize_duration<'de, D>(deserializer: D) -> Result<Duration, D::Error> 
 	where D: Deserializer<'de>
 {
 	let de = DeDuration::deserialize(deserializer)?;
 	Ok(Duration::seconds_f32(de.time / 1e3))
 }
 
+pub(crate) fn serialize_option_duration<S>(duration: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error> 
+	where S: Serializer 
+{
+	match duration {
+		Some(duration) => serialize_duration(duration, serializer),
+		None => serializer.serialize_none(),
+	}
+}
+
+pub(crate) fn deserialize_option_duration<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error> 
+	where D: Deserializer<'de>
+{
+	let de = Option::<DeDuration>::deserialize(deserializer)?;
+	Ok(de.map(|de| Duration::seconds_f32(de.time / 1e3)))
+}
+
 #[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq, Default)]
 #[serde(default)]
 /// the basic render unit
@@ -164,6 +233,14 @@ impl CanBeAnimated<'_, NabloShape> for Shape {
 pub struct ChartHistory {
 	pub high_score: usize,
 	pub high_accurcy: f32,
+	/// scoring system of `high_score`, results counted with other systems won't be merged.
+	pub scoring: ScoringSystem,
+	/// how many results are merged
+	pub play_count: usize,
+	/// best clear type ever reached, None if never played.
+	pub best_clear: Option<ClearType>,
+	/// grade of `high_score`
+	pub best_grade: Option<String>,
 }
 
 #[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
@@ -386,17 +463,21 @@ pub enum JudgeType {
 	Hold(Duration),
 	TapAndFlick,
 	/// contains where and after when the player moves to next judge field. not available in PC. 
+	///
+	/// every step should be reached before judge time plus sum of durations so far, missing steps will grade the note down.
 	Chain(Vec<(String, Duration)>),
-	/// contains where and after when the player moves to next judge field. not available in PC.
+	/// same as [`JudgeType::Chain`], but needs a press to start.
 	TapChain(Vec<(String, Duration)>),
 	/// contains which angle should player filck to, save as rad.
 	AngledFilck(f32),
 	AngledTapFilck(f32),
+	/// judged by a rule registered into [`crate::system::judge_rule::JudgeRules`] with this name.
+	Custom(String),
 }
 
 /// saves info during judging
 pub struct JudgeInfo {
-	/// saves where we judge
+	/// index of the first note of this field which hasn't been judged, notes before it are skipped while judging.
 	pub current_judge: usize,
 	/// what we need keep tracking
 	pub judge_tracks: Vec<JudgeTrack>
@@ -408,6 +489,8 @@ pub struct JudgeTrack {
 	pub linked_click: usize,
 	pub start_time: Duration,
 	pub last_position: Vec2,
+	/// how many steps of a chain note have been reached
+	pub chain_progress: usize,
 }
 
 #[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq, Default)]
@@ -565,6 +648,71 @@ pub struct ChartEditor {
 	pub show_click_effect: bool,
 }
 
+#[derive(Clone, Debug, PartialEq)]
+/// how to generate a rhythm draft from track, see [`ShapoistCore::generate_draft()`]
+pub struct DraftOptions {
+	/// which judge field should generated notes link to
+	pub judge_field_id: String,
+	/// normalized, 1.0 uses every detected onset while lower value only keeps stronger ones. by default its 0.5
+	pub density: f32,
+	/// snap notes to bpm grid with given divisions per beat, None for not snapping. by default its 2
+	pub snap: Option<u32>,
+	/// notes closer than this will be merged, by default its 100 ms
+	pub min_interval: Duration,
+	/// what kind of notes will be generated, by default its tap
+	pub judge_type: JudgeType,
+	/// how long does a generated shape fall before judge time, by default its 1 s
+	pub approach_time: Duration,
+}
+
+impl Default for DraftOptions {
+	fn default() -> Self {
+		Self {
+			judge_field_id: String::from("default"),
+			density: 0.5,
+			snap: Some(2),
+			min_interval: Duration::milliseconds(100),
+			judge_type: JudgeType::Tap,
+			approach_time: Duration::seconds(1),
+		}
+	}
+}
+
+impl DraftOptions {
+	/// the default shape of a generated note, falls from the top of chart into `target`
+	pub(crate) fn note_shape(&self, note_id: &str, judge_time: Duration, target: Vec2) -> Shape {
+		let size = 100.0;
+		let start_time = judge_time - self.approach_time;
+		Shape {
+			id: note_id.into(),
+			animation: HashMap::from([(String::from("----Shape----style----position----y"), Animation {
+				start_time,
+				start_value: -size,
+				linkers: vec!(Linker {
+					end_value: target.y - size / 2.0,
+					sustain_time: self.approach_time,
+					..Default::default()
+				})
+			})]),
+			shape: NabloShape {
+				shape: ShapeElement::Rect(Rect {
+					width_and_height: Vec2::same(size),
+					rounding: Vec2::same(10.0),
+				}),
+				style: NabloStyle {
+					clip: Area::INF,
+					position: Vec2::new(target.x - size / 2.0, -size),
+					..Default::default()
+				}
+			},
+			start_time,
+			// keeps a while after judge time, it will be kicked out once linked note is judged.
+			sustain_time: self.approach_time + Duration::milliseconds(150),
+			linked_note_id: Some(vec!(note_id.into())),
+		}
+	}
+}
+
 #[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
 /// what was we select, and for some reason we need them to keep unchanged after selected? [`String refers to id`]
 pub enum SelectUnchange {
@@ -593,11 +741,29 @@ pub struct PlayInfo {
 	pub max_combo: usize,
 	/// how to judge? in editor this will be auto.
 	pub play_mode: PlayMode,
-	/// the replay file.
+	/// replay being recorded, only filled in normal mode. finished one is in [`PlayResult::replay`].
 	pub replay: Replay,
-	/// where the audio plays
-	pub judge_vec: Vec<Judge>,
-	pub audio_manager: AudioManager,
+	/// every judgement made in this play, sorted by when it is judged.
+	pub judge_vec: Vec<JudgeRecord>,
+	/// score after each judgement
+	pub score_history: Vec<f32>,
+	/// how presses are given to notes in this play
+	pub note_lock: NoteLock,
+	/// how many events of the watching replay have been judged
+	pub(crate) replay_cursor: usize,
+	/// clicks auto play is going to make
+	pub(crate) auto_clicks: Vec<AutoClick>,
+	/// notes auto play has made clicks for
+	pub(crate) auto_started: HashSet<String>,
+	pub(crate) auto_click_id: usize,
+	/// how many hits are earlier than judge time
+	pub fast_count: usize,
+	/// how many hits are later than judge time
+	pub late_count: usize,
+	/// distribution of timing offsets
+	pub timing_histogram: TimingHistogram,
+	/// None for judging without sound
+	pub audio_manager: Option<AudioManager>,
 	pub total_notes: usize,
 	pub judged_notes: usize,
 	pub is_finished: bool,
@@ -608,7 +774,7 @@ pub struct PlayInfo {
 	pub track_path: PathBuf,
 	#[cfg(target_arch = "wasm32")]
 	pub sound_data: Vec<u8>,
-	pub(crate) judged_note_id: Vec<String>,
+	pub(crate) judged_note_id: HashSet<String>,
 	pub click_sound: StaticSoundData,
 	pub click_sound_handle: Option<StaticSoundHandle>,
 	/// sort by time
@@ -617,6 +783,192 @@ pub struct PlayInfo {
 	/// none for default, contains shader code
 	pub current_shader: Option<String>,
 	pub shaders: HashMap<String, String>,
+	/// the A–B loop for practicing, None for not looping. see [`ShapoistCore::set_ab_loop()`]
+	pub ab_loop: Option<AbLoop>,
+	/// how long do we wait before chart starts, every time conversion between timer and chart uses this.
+	pub lead_in: Duration,
+	/// judge windows used in this play.
+	pub judge_profile: JudgeWindowProfile,
+	/// how this play is scored
+	pub scoring: ScoringSystem,
+	pub life_gauge: LifeGauge,
+	/// modifiers used in this play
+	pub modifiers: Vec<Modifier>,
+	/// current life, from 0.0 to 1.0, HUDs may show this.
+	pub life: f32,
+	/// life has run out and the play is stopped
+	pub is_failed: bool,
+	/// summary of this play, setted when play finishes.
+	pub result: Option<PlayResult>,
+	/// seeked or looped plays are practice, their results won't go into [`ChartHistory`]
+	pub is_practice: bool,
+	pub(crate) is_result_recorded: bool,
+	pub(crate) track_handle: Option<StaticSoundHandle>,
+}
+
+#[derive(Clone, Debug, PartialEq, Default)]
+/// loops a section of chart, when playback reaches `end`, we will seek back to `start`.
+pub struct AbLoop {
+	/// chart time where the loop starts
+	pub start: Duration,
+	/// chart time where the loop ends
+	pub end: Duration,
+	/// how many times have we looped, starts from 0.
+	pub iteration: usize,
+	/// statistics of every finished iteration, sorted by iteration.
+	pub statistics: Vec<LoopStatistics>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+/// summary of a finished play
+pub struct PlayResult {
+	pub judges: JudgeCounts,
+	pub total_notes: usize,
+	pub max_combo: usize,
+	pub accuracy: f32,
+	pub score: f32,
+	pub scoring: ScoringSystem,
+	pub clear_type: ClearType,
+	/// judge windows used in this play
+	pub judge_profile: JudgeWindowProfile,
+	/// letter grade of score, see [`Settings::grades`]
+	pub grade: String,
+	/// modifiers used in this play, score is already multiplied.
+	pub modifiers: Vec<Modifier>,
+	/// how long has been played, in chart time.
+	#[serde(serialize_with = "serialize_duration")]
+	#[serde(deserialize_with = "deserialize_duration")]
+	pub duration: Duration,
+	/// replay of this play, only normal plays which are not practices have one.
+	#[serde(skip)]
+	pub replay: Option<Replay>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+/// lowest score to get a grade
+pub struct GradeThreshold {
+	pub grade: String,
+	/// ratio of max score of used scoring system, from 0.0 to 1.0
+	pub min_score: f32,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+/// tells which chart is played, stays the same when chart is moved.
+pub struct ChartIdentity {
+	pub song_name: String,
+	pub charter: String,
+	pub version: Version,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+/// a completed play
+pub struct PlayRecord {
+	/// when does this play finish
+	#[serde(with = "time::serde::timestamp")]
+	pub date: OffsetDateTime,
+	pub chart: ChartIdentity,
+	pub result: PlayResult,
+	/// [`Settings::offset`] while playing
+	#[serde(serialize_with = "serialize_duration")]
+	#[serde(deserialize_with = "deserialize_duration")]
+	pub offset: Duration,
+	/// modifiers used in this play
+	pub modifiers: Vec<Modifier>,
+}
+
+/// everything a replay file tells before loading the whole replay.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct ReplayHeader {
+	pub chart: ChartIdentity,
+	/// [`Chart::content_hash()`] of the played chart, before modifiers are applied.
+	pub chart_hash: String,
+	/// version of shapoist core which recorded the replay
+	pub core_version: String,
+	pub judge_profile: JudgeWindowProfile,
+	/// [`Settings::offset`] while playing
+	#[serde(serialize_with = "serialize_duration")]
+	#[serde(deserialize_with = "deserialize_duration")]
+	pub offset: Duration,
+	/// see [`Settings::player_name`]
+	pub player_name: String,
+	/// when does the play finish
+	#[serde(with = "time::serde::timestamp")]
+	pub date: OffsetDateTime,
+}
+
+impl Default for ReplayHeader {
+	fn default() -> Self {
+		Self {
+			chart: ChartIdentity::default(),
+			chart_hash: String::new(),
+			core_version: String::new(),
+			judge_profile: JudgeWindowProfile::default(),
+			offset: Duration::ZERO,
+			player_name: String::new(),
+			date: OffsetDateTime::UNIX_EPOCH,
+		}
+	}
+}
+
+impl Default for PlayRecord {
+	fn default() -> Self {
+		Self {
+			date: OffsetDateTime::UNIX_EPOCH,
+			chart: ChartIdentity::default(),
+			result: PlayResult::default(),
+			offset: Duration::ZERO,
+			modifiers: vec!(),
+		}
+	}
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+/// every completed play of current user, sorted by date.
+pub struct PlayHistory {
+	pub records: Vec<PlayRecord>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+/// how many times each [`Judge`] appears
+pub struct JudgeCounts {
+	pub immaculate: usize,
+	pub extra: usize,
+	pub normal: usize,
+	pub fade: usize,
+	pub miss: usize,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Default)]
+/// how well a chart is cleared, later ones are better.
+pub enum ClearType {
+	/// play is stopped before chart ends
+	Failed,
+	#[default] Clear,
+	/// no [`Judge::Fade`] or [`Judge::Miss`]
+	FullCombo,
+	/// every note is [`Judge::Immaculate`]
+	AllImmaculate,
+}
+
+#[derive(Clone, Debug, PartialEq, Default)]
+/// what player did in a single loop iteration
+pub struct LoopStatistics {
+	pub iteration: usize,
+	pub score: f32,
+	pub accuracy: f32,
+	pub max_combo: usize,
+	/// how many notes are there inside the loop
+	pub total_notes: usize,
+	pub judges: Vec<JudgeRecord>,
+	/// which scoring system the score is counted with
+	pub scoring: ScoringSystem,
 }
 
 #[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq, Default)]
@@ -631,14 +983,120 @@ pub enum PlayMode {
 #[serde(default)]
 /// the replay file.
 pub struct Replay {
+	/// score after each judgement
 	pub score_history: Vec<f32>,
-	pub judge_history_events: Vec<JudgeEvent>
+	/// every event judged, sorted by [`JudgeEvent::time`]
+	pub judge_history_events: Vec<JudgeEvent>,
+	/// judge windows used while recording, results are only comparable under the same profile.
+	pub judge_profile: JudgeWindowProfile,
+	/// modifiers used in the replayed play
+	pub modifiers: Vec<Modifier>,
+	/// judging and scoring of the replayed play, replays are always watched with these and [`Replay::judge_profile`].
+	pub note_lock: NoteLock,
+	pub scoring: ScoringSystem,
+	pub life_gauge: LifeGauge,
+	/// [`Settings::offset`] while playing
+	#[serde(serialize_with = "serialize_duration")]
+	#[serde(deserialize_with = "deserialize_duration")]
+	pub offset: Duration,
+	/// when does the play finish, None while recording.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	#[serde(with = "time::serde::timestamp::option")]
+	pub date: Option<OffsetDateTime>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq, Default)]
+/// presets of [`LifeGauge`]
+pub enum LifeGaugeProfile {
+	/// same as [`LifeGaugeProfile::Standard`], but play never fails.
+	#[default] NoFail,
+	Standard,
+	/// fails on first miss
+	SuddenDeath,
+	Custom(LifeGauge),
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+/// how life changes during play, life is between 0.0 and 1.0.
+pub struct LifeGauge {
+	/// life when play starts
+	pub initial: f32,
+	/// how much life does each judgement give, negative for draining.
+	pub immaculate: f32,
+	pub extra: f32,
+	pub normal: f32,
+	pub fade: f32,
+	pub miss: f32,
+	/// will play fail when life runs out?
+	pub can_fail: bool,
+}
+
+impl Default for LifeGauge {
+	fn default() -> Self {
+		Self {
+			initial: 1.0,
+			immaculate: 0.01,
+			extra: 0.005,
+			normal: 0.0,
+			fade: -0.02,
+			miss: -0.05,
+			can_fail: true,
+		}
+	}
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq, Default)]
+/// presets of [`JudgeWindows`]
+pub enum JudgeWindowProfile {
+	Lenient,
+	#[default] Standard,
+	Strict,
+	Custom(JudgeWindows),
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+/// how far from judge time can a click still get each [`Judge`], and how flicks are detected.
+pub struct JudgeWindows {
+	#[serde(serialize_with = "serialize_duration")]
+	#[serde(deserialize_with = "deserialize_duration")]
+	pub immaculate: Duration,
+	#[serde(serialize_with = "serialize_duration")]
+	#[serde(deserialize_with = "deserialize_duration")]
+	pub extra: Duration,
+	#[serde(serialize_with = "serialize_duration")]
+	#[serde(deserialize_with = "deserialize_duration")]
+	pub normal: Duration,
+	/// clicks further than this will be ignored, and notes later than this will be missed.
+	#[serde(serialize_with = "serialize_duration")]
+	#[serde(deserialize_with = "deserialize_duration")]
+	pub fade: Duration,
+	/// how long can player take to finish a flick
+	#[serde(serialize_with = "serialize_duration")]
+	#[serde(deserialize_with = "deserialize_duration")]
+	pub flick_timeout: Duration,
+	/// how far should a click move to be a flick, in pixel.
+	pub flick_distance: f32,
+}
+
+impl Default for JudgeWindows {
+	fn default() -> Self {
+		Self {
+			immaculate: Duration::milliseconds(50),
+			extra: Duration::milliseconds(70),
+			normal: Duration::milliseconds(120),
+			fade: Duration::milliseconds(150),
+			flick_timeout: Duration::milliseconds(120),
+			flick_distance: 5.0,
+		}
+	}
 }
 
 #[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq, Default)]
 /// The judgement of a [`Note`]
 pub enum Judge {
-	/// saves (now - click_time) / Duration::milis(50). 
+	/// saves how good it is, 1.0 for a click exactly on judge time and near 0.0 at the edge of [`JudgeWindows::immaculate`].
 	Immaculate(f32),
 	Extra,
 	Normal,
@@ -646,13 +1104,61 @@ pub enum Judge {
 	#[default] Miss
 }
 
+/// a click auto play is going to make
+pub(crate) struct AutoClick {
+	pub(crate) id: usize,
+	pub(crate) time: Duration,
+	/// the click lands on center of this judge field
+	pub(crate) field_id: String,
+	pub(crate) offset: Vec2,
+	pub(crate) state: ClickState,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+/// a single judgement
+pub struct JudgeRecord {
+	/// which note is judged
+	pub note_id: String,
+	pub judge: Judge,
+	/// input time minus judge time, negative means early. None for misses.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	#[serde(serialize_with = "serialize_option_duration")]
+	#[serde(deserialize_with = "deserialize_option_duration")]
+	pub offset: Option<Duration>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+/// how many judgements fall into each offset range
+pub struct TimingHistogram {
+	/// offset of the left edge of the first bucket
+	#[serde(serialize_with = "serialize_duration")]
+	#[serde(deserialize_with = "deserialize_duration")]
+	pub start: Duration,
+	#[serde(serialize_with = "serialize_duration")]
+	#[serde(deserialize_with = "deserialize_duration")]
+	pub bucket_width: Duration,
+	pub buckets: Vec<usize>,
+	/// how many offsets are earlier than the first bucket
+	pub underflow: usize,
+	/// how many offsets are later than the last bucket
+	pub overflow: usize,
+}
+
 #[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq, Default)]
 #[serde(default)]
 /// contains clicks and keypresses
 pub struct JudgeEvent {
 	/// contains both mouse clicks and touch
 	pub clicks: Vec<Click>,
-	// pub keypresses: Vec<KeyPress>,
+	/// key presses, will be judged as clicks on judge fields bound in [`Settings::key_bindings`]
+	pub keypresses: Vec<KeyPress>,
+	/// when is this event judged on play timeline, setted when recorded into a [`Replay`].
+	#[serde(skip_serializing_if = "Option::is_none")]
+	#[serde(serialize_with = "serialize_option_duration")]
+	#[serde(deserialize_with = "deserialize_option_duration")]
+	pub time: Option<Duration>,
 }
 
 #[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq, Default)]
@@ -661,7 +1167,14 @@ pub struct JudgeEvent {
 pub struct Click {
 	pub id: usize,
 	pub position: Vec2,
-	pub state: ClickState
+	pub state: ClickState,
+	/// which key does this click come from, None for mouse clicks and touch.
+	pub key: Option<Key>,
+	/// when does this click happen on the play timeline (same as [`ShapoistCore::current()`]), None for when it is judged.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	#[serde(serialize_with = "serialize_option_duration")]
+	#[serde(deserialize_with = "deserialize_option_duration")]
+	pub time: Option<Duration>,
 }
 
 #[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq, Default)]
@@ -672,17 +1185,22 @@ pub enum ClickState {
 	#[default] Released
 }
 
-// #[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq, Default)]
-// #[serde(default)]
-// /// is key press?
-// pub struct KeyPress {
-// 	pub id: usize,
-// 	/// numbers contain both numpad and key
-// 	pub key: Key,
-// 	pub is_click: bool
-// }
-
 #[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq, Default)]
+#[serde(default)]
+/// is key press?
+pub struct KeyPress {
+	/// numbers contain both numpad and key
+	pub key: Key,
+	/// pressed, held or released
+	pub state: ClickState,
+	/// when does this key press happen on the play timeline (same as [`ShapoistCore::current()`]), None for when it is judged.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	#[serde(serialize_with = "serialize_option_duration")]
+	#[serde(deserialize_with = "deserialize_option_duration")]
+	pub time: Option<Duration>,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
 /// Human readable keyname
 pub enum Key {
 	#[default] A,B,C,D,E,F,G,H,I,J,K,L,M,N,O,P,Q,R,S,T,U,V,W,X,Y,Z,One,Two,Three,Four,Five,Six,Seven,Eight,Nine,Zero,NumPad1,NumPad2,NumPad3,NumPad4,NumPad5,NumPad6,NumPad7,NumPad8,NumPad9,NumPad0,ArrowDown,
@@ -791,6 +1309,38 @@ pub struct Settings {
 	#[serde(serialize_with = "serialize_duration")]
 	#[serde(deserialize_with = "deserialize_duration")]
 	pub offset: Duration,
+	/// overrides lead in of every chart, None for using chart's own.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	#[serde(serialize_with = "serialize_option_duration")]
+	#[serde(deserialize_with = "deserialize_option_duration")]
+	pub lead_in: Option<Duration>,
+	/// overrides judge windows of every chart, None for using chart's own.
+	pub judge_windows: Option<JudgeWindowProfile>,
+	/// overrides scoring system of every chart, None for using chart's own.
+	pub scoring: Option<ScoringSystem>,
+	/// how life gauge works while playing
+	pub life_gauge: LifeGaugeProfile,
+	/// how many best performances make up player rating
+	pub rating_count: usize,
+	/// letter grades from highest to lowest, the first one whose threshold is reached will be used.
+	pub grades: Vec<GradeThreshold>,
+	/// which judge field does a key click on, saves judge field id. flicks done by keys are judged as [`Judge::Extra`] at best.
+	pub key_bindings: HashMap<Key, String>,
+	/// which note does a press go to when several notes can be reached
+	pub note_lock: NoteLock,
+	/// shown in saved replays
+	pub player_name: String,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq, Default)]
+/// which note does a press go to when several notes can be reached
+pub enum NoteLock {
+	/// the note closest to the press in time
+	#[default] Closest,
+	/// the earliest note the press reaches, later notes are locked until it is judged
+	Earliest,
+	/// every note the press reaches, a single press may judge several notes
+	Off,
 }
 
 impl Default for Settings {
@@ -806,6 +1356,15 @@ impl Default for Settings {
 			music_volume: 0.8,
 			click_sound_volume: 0.7,
 			offset: Duration::ZERO,
+			lead_in: None,
+			judge_windows: None,
+			scoring: None,
+			life_gauge: LifeGaugeProfile::default(),
+			rating_count: 30,
+			grades: GradeThreshold::default_grades(),
+			key_bindings: HashMap::new(),
+			note_lock: NoteLock::default(),
+			player_name: String::new(),
 		}
 	}
 }
\ No newline at end of file
@@ -65,6 +65,33 @@ pub(crate) fn write_file<P: AsRef<Path>>(path: P, mut input: impl Read) -> Resul
 	}
 }
 
+#[allow(dead_code)]
+pub(crate) fn append_file<P: AsRef<Path>>(path: P, mut input: impl Read) -> Result<(), Error>{
+	debug!("appending file {}", path.as_ref().display());
+	let mut buffer = vec!();
+	if let Err(e) = input.read_to_end(&mut buffer) {
+		error!("opening file failed, info: {}", e);
+		return Err(e.into())
+	};
+	let mut file = match fs::OpenOptions::new().append(true).open(&path) {
+		Ok(t) => t,
+		Err(e) => {
+			error!("opening file failed, info: {}", e);
+			return Err(e.into());
+		}
+	};
+	match file.write_all(buffer.as_slice()){
+		Ok(_) => {
+			info!("file {} appended", path.as_ref().display());
+			Ok(())
+		},
+		Err(e) => {
+			error!("appending file failed, info: {}", e);
+			Err(e.into())
+		}
+	}
+}
+
 // pub(crate) fn read_file_split<P: AsRef<Path>>(path: P) -> Result<Vec<String>, Error>{
 // 	let file_open = fs::File::open(&path);
 // 	match file_open {
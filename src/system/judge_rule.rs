@@ -0,0 +1,398 @@
+//! how each kind of [`Note`] is judged.
+//!
+//! every [`JudgeType`] is judged by a [`JudgeRule`], built-in types use rules in this module,
+//! and [`JudgeType::Custom`] notes use rules registered into [`JudgeRules`] by name.
+
+use crate::system::core_structs::Click;
+use crate::system::core_structs::ClickState;
+use crate::system::core_structs::Judge;
+use crate::system::core_structs::JudgeTrack;
+use crate::system::core_structs::JudgeType;
+use crate::system::core_structs::JudgeWindows;
+use crate::system::core_structs::Note;
+use nablo_shape::prelude::Area;
+use std::collections::HashMap;
+use std::sync::Arc;
+use time::Duration;
+
+/// what a rule wants to do with a click
+#[derive(Clone, Debug, PartialEq, Default)]
+pub enum RuleAction {
+	/// the click has nothing to do with this note
+	#[default] Ignore,
+	/// start tracking the note with this click, [`JudgeRule::track()`] will be called on following clicks.
+	Track,
+	/// judge the note right away
+	Judge(Judge),
+}
+
+/// what a rule can see while judging
+pub struct JudgeContext<'a> {
+	/// when does the click happen, or current time if there is no click.
+	pub time: Duration,
+	pub windows: &'a JudgeWindows,
+	/// areas of every judge field, keyed by judge field id.
+	pub areas: &'a HashMap<String, Area>,
+}
+
+/// decides how a kind of note is judged.
+///
+/// the judge pipeline gives each rule clicks that are inside the judge field of the note and inside [`JudgeWindows::fade`],
+/// presses are given to at most one note according to [`crate::system::core_structs::NoteLock`].
+pub trait JudgeRule: Send + Sync {
+	/// does this note need a press to start? every press can only be owned by one note.
+	fn needs_press(&self, note: &Note) -> bool;
+	/// a click reaches this note, only pressed clicks are given if [`JudgeRule::needs_press()`] is true.
+	fn start(&self, note: &Note, click: &Click, context: &JudgeContext) -> RuleAction;
+	/// a click linked to a tracked note changes, return [`Option::Some`] to finish tracking.
+	fn track(&self, _note: &Note, _track: &mut JudgeTrack, _click: &Click, _context: &JudgeContext) -> Option<Judge> {
+		None
+	}
+	/// called on every judge for tracked notes, even if their click sends nothing. return [`Option::Some`] to finish tracking.
+	fn check_track(&self, _note: &Note, _track: &JudgeTrack, _context: &JudgeContext) -> Option<Judge> {
+		None
+	}
+	/// nothing has started this note yet, should it be missed?
+	fn is_missed(&self, note: &Note, context: &JudgeContext) -> bool {
+		context.time - note.judge_time > context.windows.fade
+	}
+}
+
+/// every rule we can use while playing
+#[derive(Clone, Default)]
+pub struct JudgeRules {
+	custom: HashMap<String, Arc<dyn JudgeRule>>,
+}
+
+impl std::fmt::Debug for JudgeRules {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		f.debug_struct("JudgeRules").field("custom", &self.custom.keys().collect::<Vec<_>>()).finish()
+	}
+}
+
+impl JudgeRules {
+	/// register a rule for [`JudgeType::Custom`] notes named `name`, returns the rule it replaces.
+	pub fn register(&mut self, name: impl Into<String>, rule: impl JudgeRule + 'static) -> Option<Arc<dyn JudgeRule>> {
+		self.custom.insert(name.into(), Arc::new(rule))
+	}
+
+	/// remove a custom rule
+	pub fn unregister(&mut self, name: &str) -> Option<Arc<dyn JudgeRule>> {
+		self.custom.remove(name)
+	}
+
+	/// names of every registered custom rule
+	pub fn custom_names(&self) -> impl Iterator<Item = &String> {
+		self.custom.keys()
+	}
+
+	/// find the rule of given type, custom notes without registered rule can never be started and will be missed.
+	pub fn get(&self, judge_type: &JudgeType) -> &dyn JudgeRule {
+		match judge_type {
+			JudgeType::Tap => &TapRule,
+			JudgeType::Slide => &SlideRule,
+			JudgeType::Flick | JudgeType::TapAndFlick => &FlickRule,
+			JudgeType::Hold(_) => &HoldRule,
+			JudgeType::Chain(_) | JudgeType::TapChain(_) => &ChainRule,
+			JudgeType::AngledFilck(_) | JudgeType::AngledTapFilck(_) => &AngledFlickRule,
+			JudgeType::Custom(name) => match self.custom.get(name) {
+				Some(rule) => rule.as_ref(),
+				None => &UnknownRule,
+			}
+		}
+	}
+}
+
+/// judged once pressed
+pub struct TapRule;
+
+impl JudgeRule for TapRule {
+	fn needs_press(&self, _: &Note) -> bool {
+		true
+	}
+
+	fn start(&self, note: &Note, _: &Click, context: &JudgeContext) -> RuleAction {
+		RuleAction::Judge(judge_by_delta(context.time - note.judge_time, context.windows))
+	}
+}
+
+/// judged once touched
+pub struct SlideRule;
+
+impl JudgeRule for SlideRule {
+	fn needs_press(&self, _: &Note) -> bool {
+		false
+	}
+
+	fn start(&self, _: &Note, click: &Click, _: &JudgeContext) -> RuleAction {
+		match click.state {
+			ClickState::Released => RuleAction::Ignore,
+			_ => RuleAction::Judge(Judge::Immaculate(1.0)),
+		}
+	}
+}
+
+/// [`JudgeType::Flick`] and [`JudgeType::TapAndFlick`]
+pub struct FlickRule;
+
+impl JudgeRule for FlickRule {
+	fn needs_press(&self, note: &Note) -> bool {
+		matches!(note.judge_type, JudgeType::TapAndFlick)
+	}
+
+	fn start(&self, _: &Note, _: &Click, _: &JudgeContext) -> RuleAction {
+		RuleAction::Track
+	}
+
+	fn track(&self, _: &Note, track: &mut JudgeTrack, click: &Click, context: &JudgeContext) -> Option<Judge> {
+		let mut judge = None;
+		// keys can't move, so holding or releasing it counts as a flick, but never a perfect one.
+		if click.key.is_some() {
+			judge = Some(Judge::Extra);
+		}else if (click.position - track.last_position).len() > context.windows.flick_distance {
+			judge = Some(Judge::Immaculate(1.0));
+		}
+		if (context.time - track.start_time).abs() > context.windows.flick_timeout {
+			judge = Some(Judge::Miss);
+		}
+		judge
+	}
+}
+
+/// [`JudgeType::Hold`]
+pub struct HoldRule;
+
+impl JudgeRule for HoldRule {
+	fn needs_press(&self, _: &Note) -> bool {
+		true
+	}
+
+	fn start(&self, _: &Note, _: &Click, _: &JudgeContext) -> RuleAction {
+		RuleAction::Track
+	}
+
+	fn track(&self, note: &Note, track: &mut JudgeTrack, click: &Click, context: &JudgeContext) -> Option<Judge> {
+		let JudgeType::Hold(sustain) = note.judge_type else {
+			return None
+		};
+		let judge_check = || judge_by_delta(track.start_time - note.judge_time, context.windows);
+		let percent = ((context.time - note.judge_time) / sustain) as f32;
+		if percent > 1.0 {
+			let mut judge = judge_check();
+			if let Judge::Immaculate(inner) = &mut judge {
+				*inner *= 0.995
+			}
+			Some(judge)
+		}else if percent > 0.0 {
+			if let ClickState::Released = click.state {
+				if percent < 0.8 {
+					Some(Judge::Miss)
+				}else {
+					let mut judge = judge_check();
+					if let Judge::Immaculate(inner) = &mut judge {
+						*inner *= percent
+					}
+					Some(judge)
+				}
+			}else {
+				None
+			}
+		}else {
+			None
+		}
+	}
+}
+
+/// [`JudgeType::Chain`] and [`JudgeType::TapChain`]
+pub struct ChainRule;
+
+impl JudgeRule for ChainRule {
+	fn needs_press(&self, note: &Note) -> bool {
+		matches!(note.judge_type, JudgeType::TapChain(_))
+	}
+
+	fn start(&self, _: &Note, click: &Click, _: &JudgeContext) -> RuleAction {
+		// a lift-off sends nothing after, so it can't start a chain
+		match click.state {
+			ClickState::Released => RuleAction::Ignore,
+			_ => RuleAction::Track,
+		}
+	}
+
+	fn track(&self, note: &Note, track: &mut JudgeTrack, click: &Click, context: &JudgeContext) -> Option<Judge> {
+		let (JudgeType::Chain(steps) | JudgeType::TapChain(steps)) = &note.judge_type else {
+			return None
+		};
+		if let Some((field_id, _)) = steps.get(track.chain_progress) {
+			if context.areas.get(field_id).is_some_and(|area| area.is_point_inside(&click.position)) {
+				track.chain_progress += 1;
+			}
+		}
+		if track.chain_progress >= steps.len() || matches!(click.state, ClickState::Released) || context.time > chain_deadline(note.judge_time, steps, track.chain_progress, context.windows) {
+			Some(chain_judge(track.start_time - note.judge_time, steps, track.chain_progress, context.windows))
+		}else {
+			None
+		}
+	}
+
+	fn check_track(&self, note: &Note, track: &JudgeTrack, context: &JudgeContext) -> Option<Judge> {
+		let (JudgeType::Chain(steps) | JudgeType::TapChain(steps)) = &note.judge_type else {
+			return None
+		};
+		if context.time > chain_deadline(note.judge_time, steps, track.chain_progress, context.windows) {
+			Some(chain_judge(track.start_time - note.judge_time, steps, track.chain_progress, context.windows))
+		}else {
+			None
+		}
+	}
+}
+
+/// every step of a chain note should be reached before its own time plus fade window
+fn chain_deadline(judge_time: Duration, steps: &[(String, Duration)], progress: usize, windows: &JudgeWindows) -> Duration {
+	judge_time + steps.iter().take(progress + 1).map(|(_, sustain)| *sustain).sum::<Duration>() + windows.fade
+}
+
+/// grade a chain note by when it started and how many steps are reached
+fn chain_judge(start_delta: Duration, steps: &[(String, Duration)], progress: usize, windows: &JudgeWindows) -> Judge {
+	let completion = if steps.is_empty() {
+		1.0
+	}else {
+		progress as f32 / steps.len() as f32
+	};
+	judge_by_delta(start_delta, windows).capped_by_completion(completion)
+}
+
+/// [`JudgeType::AngledFilck`] and [`JudgeType::AngledTapFilck`]
+pub struct AngledFlickRule;
+
+impl JudgeRule for AngledFlickRule {
+	fn needs_press(&self, note: &Note) -> bool {
+		matches!(note.judge_type, JudgeType::AngledTapFilck(_))
+	}
+
+	fn start(&self, _: &Note, _: &Click, _: &JudgeContext) -> RuleAction {
+		RuleAction::Track
+	}
+
+	fn track(&self, note: &Note, track: &mut JudgeTrack, click: &Click, context: &JudgeContext) -> Option<Judge> {
+		let (JudgeType::AngledFilck(angle) | JudgeType::AngledTapFilck(angle)) = note.judge_type else {
+			return None
+		};
+		let mut judge = None;
+		let delta = click.position - track.last_position;
+		let radio = (delta.angle() - angle).abs() / (std::f32::consts::PI / 12.0);
+		// keys can't aim, so holding or releasing it counts as a flick, but never a perfect one.
+		if click.key.is_some() {
+			judge = Some(Judge::Extra);
+		}else if delta.len() > context.windows.flick_distance && radio < 1.0 {
+			judge = Some(Judge::Immaculate(1.0 - radio));
+		}
+		if (context.time - track.start_time).abs() > context.windows.flick_timeout {
+			judge = Some(Judge::Miss);
+		}
+		judge
+	}
+}
+
+/// used by custom notes without a registered rule
+struct UnknownRule;
+
+impl JudgeRule for UnknownRule {
+	fn needs_press(&self, _: &Note) -> bool {
+		false
+	}
+
+	fn start(&self, _: &Note, _: &Click, _: &JudgeContext) -> RuleAction {
+		RuleAction::Ignore
+	}
+}
+
+/// judge by how far is the click from judge time
+pub fn judge_by_delta(delta: Duration, windows: &JudgeWindows) -> Judge {
+	let delta = delta.abs();
+	if delta < windows.immaculate {
+		Judge::Immaculate(1.0 - (delta / windows.immaculate) as f32)
+	}else if delta < windows.extra {
+		Judge::Extra
+	}else if delta < windows.normal {
+		Judge::Normal
+	}else if delta < windows.fade {
+		Judge::Fade
+	}else {
+		Judge::Miss
+	}
+}
+
+impl Judge {
+	/// higher is better
+	pub(crate) fn level(&self) -> usize {
+		match self {
+			Judge::Immaculate(_) => 4,
+			Judge::Extra => 3,
+			Judge::Normal => 2,
+			Judge::Fade => 1,
+			Judge::Miss => 0,
+		}
+	}
+
+	/// grade down according to how much of the note is done, used by chain notes.
+	pub fn capped_by_completion(self, completion: f32) -> Judge {
+		let cap = if completion >= 1.0 {
+			return self
+		}else if completion >= 0.75 {
+			Judge::Extra
+		}else if completion >= 0.5 {
+			Judge::Normal
+		}else if completion > 0.0 {
+			Judge::Fade
+		}else {
+			Judge::Miss
+		};
+		if self.level() > cap.level() {
+			cap
+		}else {
+			self
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn steps(count: usize) -> Vec<(String, Duration)> {
+		(0..count).map(|i| (format!("field{}", i), Duration::milliseconds(100))).collect()
+	}
+
+	#[test]
+	fn chain_grades_down_by_completion() {
+		let steps = steps(4);
+		let windows = JudgeWindows::default();
+		assert_eq!(chain_judge(Duration::ZERO, &steps, 4, &windows), Judge::Immaculate(1.0));
+		assert_eq!(chain_judge(Duration::ZERO, &steps, 3, &windows), Judge::Extra);
+		assert_eq!(chain_judge(Duration::ZERO, &steps, 2, &windows), Judge::Normal);
+		assert_eq!(chain_judge(Duration::ZERO, &steps, 1, &windows), Judge::Fade);
+		assert_eq!(chain_judge(Duration::ZERO, &steps, 0, &windows), Judge::Miss);
+	}
+
+	#[test]
+	fn chain_keeps_worse_start_judge() {
+		let steps = steps(4);
+		let windows = JudgeWindows::default();
+		// started late enough for normal, completing more can't raise it
+		assert_eq!(chain_judge(Duration::milliseconds(100), &steps, 4, &windows), Judge::Normal);
+		assert_eq!(chain_judge(Duration::milliseconds(100), &steps, 3, &windows), Judge::Normal);
+		assert_eq!(chain_judge(Duration::milliseconds(-100), &steps, 1, &windows), Judge::Fade);
+		assert_eq!(chain_judge(Duration::ZERO, &[], 0, &windows), Judge::Immaculate(1.0));
+	}
+
+	#[test]
+	fn chain_deadline_follows_progress() {
+		let steps = steps(3);
+		let windows = JudgeWindows::default();
+		assert_eq!(chain_deadline(Duration::seconds(1), &steps, 0, &windows), Duration::milliseconds(1250));
+		assert_eq!(chain_deadline(Duration::seconds(1), &steps, 2, &windows), Duration::milliseconds(1450));
+		// finished chains don't go beyond the last step
+		assert_eq!(chain_deadline(Duration::seconds(1), &steps, 3, &windows), Duration::milliseconds(1450));
+	}
+}
@@ -6,6 +6,12 @@ mod io_functions;
 pub mod core_structs;
 pub mod timer;
 pub mod command;
+pub mod audio_analysis;
+pub mod chart_analysis;
+pub mod judge_rule;
+pub mod modifier;
+pub mod scoring;
+pub mod rating;
 
 use crate::system::command::CommandError;
 
@@ -36,6 +42,8 @@ pub enum Error {
 	PlayError(#[from] PlayError),
 	#[error("Chart Edit Error, info: {0}")]
 	ChartEditError(#[from] ChartEditError),
+	#[error("Replay Error, info: {0}")]
+	ReplayError(#[from] ReplayError),
 	#[error("the funtion you call is not available on {0} platform")]
 	PlatformUnsupport(String)
 }
@@ -53,7 +61,9 @@ pub enum ChartError {
 	#[error("Cant read music source, info: {0}")]
 	MusicSourceCantRead(#[from] kira::sound::FromFileError),
 	#[error("Cant read music source, info: {0}")]
-	MusicSourceCantReadString(String)
+	MusicSourceCantReadString(String),
+	#[error("Cant find a clear beat in music source")]
+	NoClearBeat,
 }
 
 #[non_exhaustive]
@@ -72,6 +82,8 @@ pub enum PlayError {
 	MusicPlayFailed(#[from] kira::manager::error::PlaySoundError<()>),
 	#[error("error during using kira, info: {0}")]
 	KiraError(#[from] kira::CommandError),
+	#[error("loop start should be earlier than loop end")]
+	InvalidLoopRange,
 }
 
 #[non_exhaustive]
@@ -82,4 +94,20 @@ pub enum ChartEditError {
 	MissingInfo,
 	#[error("not in edit mode")]
 	NotInEditMode,
+	#[error("judge field {0} not found")]
+	JudgeFieldNotFound(String),
+}
+
+#[non_exhaustive]
+/// possible reasons that will appear when saving or loading replays
+#[derive(thiserror::Error, Debug)]
+pub enum ReplayError {
+	#[error("no finished normal play to save")]
+	NothingToSave,
+	#[error("replay is recorded on {0}, which is not the loaded chart")]
+	ChartMismatch(String),
+	#[error("chart {0} has been modified since the replay was recorded")]
+	ChartModified(String),
+	#[error("replay is recorded by shapoist core {0}, which judges differently from this one")]
+	VersionMismatch(String),
 }
\ No newline at end of file
@@ -0,0 +1,266 @@
+//! gameplay modifiers, they change how a chart is played without editing it.
+
+use crate::system::core_structs::Chart;
+use crate::system::core_structs::JudgeField;
+use crate::system::core_structs::JudgeType;
+use crate::system::core_structs::Note;
+use crate::system::core_structs::Shape;
+use nablo_data::CanBeAnimated;
+use nablo_shape::prelude::Vec2;
+use nablo_shape::shape::animation::Animation;
+use nablo_shape::prelude::Linker;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use time::Duration;
+
+const SHAPE_POSITION_X: &str = "----Shape----style----position----x";
+const SHAPE_POSITION_Y: &str = "----Shape----style----position----y";
+const SHAPE_ALPHA: &str = "----Shape----style----fill----color----a";
+/// how long before judge time do note shapes start to fade out with [`Modifier::Hidden`]
+const HIDDEN_START: Duration = Duration::milliseconds(600);
+/// how long does the fade out take with [`Modifier::Hidden`]
+const HIDDEN_FADE: Duration = Duration::milliseconds(300);
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq, Eq, Hash)]
+/// a gameplay modifier, passed to [`crate::system::core_structs::ShapoistCore::play()`]
+pub enum Modifier {
+	/// mirror judge fields and shapes horizontally across [`Chart::size`]
+	Mirror,
+	/// note shapes fade out before reaching judge time
+	Hidden,
+	/// notes are moved between equivalent judge fields, which have same size and lifetime. contains the seed, so replays get the same chart.
+	///
+	/// seed is a [`u32`] since toml can't save integers larger than [`i64::MAX`].
+	Shuffle(u32),
+	/// flick notes are played automatically
+	AutoFlick,
+	/// life gauge never fails the play
+	NoFail,
+}
+
+impl Modifier {
+	/// score is multiplied by this, except scores counted with [`crate::system::scoring::ScoringSystem::ExScore`].
+	pub fn score_multiplier(&self) -> f32 {
+		match self {
+			Self::Mirror => 1.0,
+			Self::Hidden => 1.06,
+			Self::Shuffle(_) => 1.0,
+			Self::AutoFlick => 0.5,
+			Self::NoFail => 0.5,
+		}
+	}
+
+	/// product of multipliers of every modifier
+	pub fn total_multiplier(modifiers: &[Self]) -> f32 {
+		modifiers.iter().map(|modifier| modifier.score_multiplier()).product()
+	}
+
+	/// is this note played automatically with [`Modifier::AutoFlick`]?
+	pub fn is_flick(note: &Note) -> bool {
+		matches!(note.judge_type, JudgeType::Flick | JudgeType::TapAndFlick | JudgeType::AngledFilck(_) | JudgeType::AngledTapFilck(_))
+	}
+}
+
+/// get the chart a play actually uses
+pub(crate) fn apply_modifiers(chart: &Chart, modifiers: &[Modifier]) -> Chart {
+	let mut chart = chart.clone();
+	for modifier in modifiers {
+		match modifier {
+			Modifier::Mirror => mirror(&mut chart),
+			Modifier::Hidden => hidden(&mut chart),
+			Modifier::Shuffle(seed) => shuffle(&mut chart, *seed),
+			Modifier::AutoFlick | Modifier::NoFail => {},
+		}
+	}
+	chart
+}
+
+fn map_animation(animation: &mut Animation, map: impl Fn(f32) -> f32) {
+	animation.start_value = map(animation.start_value);
+	for linker in &mut animation.linkers {
+		linker.end_value = map(linker.end_value);
+	}
+}
+
+/// mirror animations of x position and rotation, whatever the animated type is.
+fn mirror_animations(animations: &mut HashMap<String, Animation>, map: impl Fn(f32) -> f32) {
+	for (key, animation) in animations {
+		if key.ends_with("----position----x") {
+			map_animation(animation, &map);
+		}else if key.ends_with("----rotate") {
+			map_animation(animation, |rotate| -rotate);
+		}
+	}
+}
+
+fn mirror(chart: &mut Chart) {
+	let width = chart.size.x;
+	for field in chart.judge_fields.values_mut() {
+		// keeps center of the field mirrored, whatever its origin is.
+		let offset = field.area().center().x - field.inner.position.x;
+		let map = |x: f32| width - x - 2.0 * offset;
+		field.inner.position.x = map(field.inner.position.x);
+		field.inner.rotate = -field.inner.rotate;
+		mirror_animations(field.get_animation_map(), map);
+	}
+	for shape in chart.shapes.values_mut() {
+		let offset = shape.shape.get_area().center().x - shape.shape.style.position.x;
+		let map = |x: f32| width - x - 2.0 * offset;
+		shape.shape.style.position.x = map(shape.shape.style.position.x);
+		shape.shape.style.rotate = -shape.shape.style.rotate;
+		mirror_animations(shape.get_animation_map(), map);
+	}
+	for note in chart.notes.values_mut() {
+		note.click_effect_position.x = width - note.click_effect_position.x;
+		if let JudgeType::AngledFilck(angle) | JudgeType::AngledTapFilck(angle) = &mut note.judge_type {
+			*angle = std::f32::consts::PI - *angle;
+		}
+	}
+}
+
+fn hidden(chart: &mut Chart) {
+	for shape in chart.shapes.values_mut() {
+		let Some(linked) = &shape.linked_note_id else {
+			continue;
+		};
+		let Some(judge_time) = linked.iter().filter_map(|id| chart.notes.get(id)).map(|note| note.judge_time).min() else {
+			continue;
+		};
+		let fade_start = judge_time - HIDDEN_START;
+		let animation = match shape.animation.remove(SHAPE_ALPHA) {
+			Some(animation) => fade_out(animation, fade_start),
+			None => Animation {
+				start_time: fade_start,
+				start_value: 255.0,
+				linkers: vec!(Linker {
+					end_value: 0.0,
+					sustain_time: HIDDEN_FADE,
+					..Default::default()
+				}),
+			},
+		};
+		shape.animation.insert(SHAPE_ALPHA.into(), animation);
+	}
+}
+
+/// keeps the animation until `fade_start`, then fades out from wherever it is.
+fn fade_out(animation: Animation, fade_start: Duration) -> Animation {
+	let mut time = animation.start_time;
+	let mut value = animation.start_value;
+	let mut linkers = vec!();
+	for linker in animation.linkers {
+		if time + linker.sustain_time <= fade_start {
+			time += linker.sustain_time;
+			value = linker.end_value;
+			linkers.push(linker);
+			continue;
+		}
+		if time < fade_start {
+			// cut the linker at fade start, treated as linear here
+			let progress = ((fade_start - time) / linker.sustain_time) as f32;
+			value += (linker.end_value - value) * progress;
+			linkers.push(Linker {
+				end_value: value,
+				sustain_time: fade_start - time,
+				..Default::default()
+			});
+			time = fade_start;
+		}
+		break;
+	}
+	if time < fade_start {
+		// holds the last value until fade start
+		linkers.push(Linker {
+			end_value: value,
+			sustain_time: fade_start - time,
+			..Default::default()
+		});
+	}
+	linkers.push(Linker {
+		end_value: 0.0,
+		sustain_time: HIDDEN_FADE,
+		..Default::default()
+	});
+	Animation {
+		start_time: animation.start_time.min(fade_start),
+		start_value: animation.start_value,
+		linkers,
+	}
+}
+
+fn shuffle(chart: &mut Chart, seed: u32) {
+	let mut rng = StdRng::seed_from_u64(u64::from(seed));
+	// fields with same size and lifetime are equivalent
+	let mut groups: Vec<Vec<String>> = vec!();
+	let mut ids: Vec<&String> = chart.judge_fields.keys().collect();
+	ids.sort();
+	for id in ids {
+		let field = &chart.judge_fields[id];
+		match groups.iter_mut().find(|group| is_equivalent(&chart.judge_fields[&group[0]], field)) {
+			Some(group) => group.push(id.clone()),
+			None => groups.push(vec!(id.clone())),
+		}
+	}
+	let mut map: HashMap<String, String> = HashMap::new();
+	for group in groups {
+		let mut shuffled = group.clone();
+		shuffled.shuffle(&mut rng);
+		map.extend(group.into_iter().zip(shuffled));
+	}
+	let centers: HashMap<String, Vec2> = chart.judge_fields.iter().map(|(id, field)| (id.clone(), field.area().center())).collect();
+	// a shape may be linked to several notes, it only follows the first one
+	let mut moved_shapes: HashSet<String> = HashSet::new();
+	let mut note_ids: Vec<String> = chart.notes.keys().cloned().collect();
+	note_ids.sort();
+	for note_id in note_ids {
+		let Some(note) = chart.notes.get_mut(&note_id) else {
+			continue;
+		};
+		let Some(target) = map.get(&note.judge_field_id) else {
+			continue;
+		};
+		let delta = centers[target] - centers[&note.judge_field_id];
+		note.judge_field_id = target.clone();
+		note.click_effect_position = note.click_effect_position + delta;
+		if let JudgeType::Chain(steps) | JudgeType::TapChain(steps) = &mut note.judge_type {
+			for (field_id, _) in steps {
+				if let Some(target) = map.get(field_id) {
+					*field_id = target.clone();
+				}
+			}
+		}
+		// shapes of the note follow it to the new field
+		if let Some(linked) = &note.linked_shape {
+			for id in linked {
+				if !moved_shapes.insert(id.clone()) {
+					continue;
+				}
+				if let Some(shape) = chart.shapes.get_mut(id) {
+					move_shape(shape, delta);
+				}
+			}
+		}
+	}
+}
+
+fn is_equivalent(a: &JudgeField, b: &JudgeField) -> bool {
+	a.inner.min == b.inner.min &&
+	a.inner.max == b.inner.max &&
+	a.inner.scale == b.inner.scale &&
+	a.inner.rotate == b.inner.rotate &&
+	a.start_time == b.start_time &&
+	a.sustain_time == b.sustain_time
+}
+
+fn move_shape(shape: &mut Shape, delta: Vec2) {
+	shape.shape.style.position = shape.shape.style.position + delta;
+	if let Some(animation) = shape.animation.get_mut(SHAPE_POSITION_X) {
+		map_animation(animation, |x| x + delta.x);
+	}
+	if let Some(animation) = shape.animation.get_mut(SHAPE_POSITION_Y) {
+		map_animation(animation, |y| y + delta.y);
+	}
+}
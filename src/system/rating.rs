@@ -0,0 +1,54 @@
+//! player rating, computed from results and difficulties of charts.
+
+use crate::system::core_structs::ChartIdentity;
+use crate::system::core_structs::ClearType;
+use crate::system::core_structs::Diffculty;
+use crate::system::core_structs::PlayResult;
+use time::OffsetDateTime;
+
+/// accuracy lower than this gives no performance
+const MIN_ACCURACY: f32 = 0.55;
+
+/// how well a result is played, used by rating
+#[derive(Clone, Debug, PartialEq)]
+pub struct Performance {
+	pub chart: ChartIdentity,
+	/// when is the result made
+	pub date: OffsetDateTime,
+	pub value: f32,
+}
+
+impl Diffculty {
+	/// single number of this difficulty, None if it can't be read as a number.
+	pub fn level(&self) -> Option<f32> {
+		match self {
+			Self::Shapoist(rhythm, movement) => Some(rhythm.max(*movement)),
+			Self::Other(inner) => inner.trim().parse().ok(),
+		}
+	}
+}
+
+/// performance of a result on a chart with given difficulty, None if difficulty is not a number.
+///
+/// accuracy is scaled from 55% to 100%, clearing fully gives a small bonus and failing halves it.
+pub fn performance_of(result: &PlayResult, diffculty: &Diffculty) -> Option<f32> {
+	let level = diffculty.level()?;
+	let accuracy = ((result.accuracy - MIN_ACCURACY) / (1.0 - MIN_ACCURACY)).clamp(0.0, 1.0);
+	let bonus = match result.clear_type {
+		ClearType::Failed => 0.5,
+		ClearType::Clear => 1.0,
+		ClearType::FullCombo => 1.05,
+		ClearType::AllImmaculate => 1.1,
+	};
+	Some(level * accuracy.powi(2) * bonus)
+}
+
+/// average of best `count` performances, missing ones count as zero.
+pub fn rating_of(performances: &[Performance], count: usize) -> f32 {
+	if count == 0 {
+		return 0.0;
+	}
+	let mut values: Vec<f32> = performances.iter().map(|performance| performance.value).collect();
+	values.sort_by(|a, b| b.total_cmp(a));
+	values.iter().take(count).sum::<f32>() / count as f32
+}
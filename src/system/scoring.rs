@@ -0,0 +1,138 @@
+//! how judgements are turned into accuracy and score.
+
+use crate::system::core_structs::Judge;
+
+/// highest score a play can get with [`ScoringSystem::Standard`], [`ScoringSystem::AccuracyOnly`] and [`ScoringSystem::ComboWeighted`]
+pub const MAX_SCORE: f32 = 2e7;
+
+/// how far a play has gone, used to caculate score
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct ScoreProgress {
+	/// average accuracy of judged notes
+	pub accuracy: f32,
+	pub judged_notes: usize,
+	pub total_notes: usize,
+	pub combo: usize,
+	pub max_combo: usize,
+}
+
+/// a way to count score
+pub trait Scoring {
+	/// how much accuracy is a judgement worth, from 0.0 to 1.0
+	fn accuracy_of(&self, judge: &Judge) -> f32;
+	/// score of current progress
+	fn score(&self, progress: &ScoreProgress) -> f32;
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq, Default)]
+/// built-in scoring systems, can be selected by [`crate::system::core_structs::ChartInfo::scoring`] or [`crate::system::core_structs::Settings::scoring`]
+pub enum ScoringSystem {
+	/// 90% of [`MAX_SCORE`] from accuracy and 10% from max combo
+	#[default] Standard,
+	/// 2 points for each [`Judge::Immaculate`] and 1 point for each [`Judge::Extra`]
+	ExScore,
+	/// every point of [`MAX_SCORE`] comes from accuracy
+	AccuracyOnly,
+	/// half of [`MAX_SCORE`] from accuracy and half from max combo
+	ComboWeighted,
+}
+
+impl ScoringSystem {
+	/// max score a full play can reach
+	pub fn max_score(&self, total_notes: usize) -> f32 {
+		match self {
+			Self::ExScore => total_notes as f32 * 2.0,
+			_ => MAX_SCORE,
+		}
+	}
+}
+
+impl Scoring for ScoringSystem {
+	fn accuracy_of(&self, judge: &Judge) -> f32 {
+		match self {
+			Self::ExScore => match judge {
+				Judge::Immaculate(_) => 1.0,
+				Judge::Extra => 0.5,
+				_ => 0.0,
+			},
+			_ => match judge {
+				Judge::Immaculate(inner) => 0.8 + inner * 0.2,
+				Judge::Extra => 0.7,
+				Judge::Normal => 0.3,
+				Judge::Fade => 0.1,
+				Judge::Miss => 0.0
+			},
+		}
+	}
+
+	fn score(&self, progress: &ScoreProgress) -> f32 {
+		let accuracy_part = progress.accuracy * progress.judged_notes as f32 / progress.total_notes as f32;
+		let combo_part = progress.max_combo as f32 / progress.total_notes as f32;
+		match self {
+			Self::Standard => accuracy_part * MAX_SCORE * 0.9 + combo_part * MAX_SCORE * 0.1,
+			Self::ExScore => (progress.accuracy * progress.judged_notes as f32 * 2.0).round(),
+			Self::AccuracyOnly => accuracy_part * MAX_SCORE,
+			Self::ComboWeighted => accuracy_part * MAX_SCORE * 0.5 + combo_part * MAX_SCORE * 0.5,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn progress(accuracy: f32, judged_notes: usize, max_combo: usize) -> ScoreProgress {
+		ScoreProgress {
+			accuracy,
+			judged_notes,
+			total_notes: 100,
+			combo: max_combo,
+			max_combo,
+		}
+	}
+
+	#[test]
+	fn full_play_reaches_max_score() {
+		for scoring in [ScoringSystem::Standard, ScoringSystem::AccuracyOnly, ScoringSystem::ComboWeighted, ScoringSystem::ExScore] {
+			let accuracy = scoring.accuracy_of(&Judge::Immaculate(1.0));
+			assert_eq!(accuracy, 1.0);
+			assert_eq!(scoring.score(&progress(accuracy, 100, 100)), scoring.max_score(100), "{:?}", scoring);
+		}
+	}
+
+	#[test]
+	fn score_splits_between_accuracy_and_combo() {
+		// every note judged at half accuracy, longest combo covers half of chart
+		let progress = progress(0.5, 100, 50);
+		assert_eq!(ScoringSystem::Standard.score(&progress), MAX_SCORE * (0.5 * 0.9 + 0.5 * 0.1));
+		assert_eq!(ScoringSystem::AccuracyOnly.score(&progress), MAX_SCORE * 0.5);
+		assert_eq!(ScoringSystem::ComboWeighted.score(&progress), MAX_SCORE * 0.5);
+	}
+
+	#[test]
+	fn unjudged_notes_count_as_nothing() {
+		assert_eq!(ScoringSystem::AccuracyOnly.score(&progress(1.0, 25, 25)), MAX_SCORE * 0.25);
+		assert_eq!(ScoringSystem::Standard.score(&progress(0.0, 0, 0)), 0.0);
+	}
+
+	#[test]
+	fn ex_score_counts_points() {
+		let scoring = ScoringSystem::ExScore;
+		assert_eq!(scoring.accuracy_of(&Judge::Extra), 0.5);
+		assert_eq!(scoring.accuracy_of(&Judge::Normal), 0.0);
+		// 3 immaculate and 1 extra
+		let accuracy = (3.0 + 0.5) / 4.0;
+		assert_eq!(scoring.score(&progress(accuracy, 4, 4)), 7.0);
+		assert_eq!(scoring.max_score(4), 8.0);
+	}
+
+	#[test]
+	fn accuracy_follows_judge() {
+		let scoring = ScoringSystem::Standard;
+		assert_eq!(scoring.accuracy_of(&Judge::Immaculate(0.0)), 0.8);
+		assert_eq!(scoring.accuracy_of(&Judge::Extra), 0.7);
+		assert_eq!(scoring.accuracy_of(&Judge::Normal), 0.3);
+		assert_eq!(scoring.accuracy_of(&Judge::Fade), 0.1);
+		assert_eq!(scoring.accuracy_of(&Judge::Miss), 0.0);
+	}
+}